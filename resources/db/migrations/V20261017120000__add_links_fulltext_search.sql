@@ -0,0 +1,33 @@
+CREATE VIRTUAL TABLE IF NOT EXISTS links_fts USING fts5
+(
+  name,
+  description,
+  href,
+  content = 'links',
+  content_rowid = 'id',
+  prefix = '2 3'
+);
+
+-- external content table needs to be kept in sync with links by triggers.
+-- note that tombstones are indexed too, visibility is decided by joining with links.
+
+CREATE TRIGGER links_fts_insert AFTER INSERT ON links
+BEGIN
+    INSERT INTO links_fts(rowid, name, description, href) VALUES (NEW.id, NEW.name, NEW.description, NEW.href);
+END;
+
+CREATE TRIGGER links_fts_delete AFTER DELETE ON links
+BEGIN
+    INSERT INTO links_fts(links_fts, rowid, name, description, href) VALUES ('delete', OLD.id, OLD.name, OLD.description, OLD.href);
+END;
+
+CREATE TRIGGER links_fts_update AFTER UPDATE OF name, description, href ON links
+BEGIN
+    INSERT INTO links_fts(links_fts, rowid, name, description, href) VALUES ('delete', OLD.id, OLD.name, OLD.description, OLD.href);
+    INSERT INTO links_fts(rowid, name, description, href) VALUES (NEW.id, NEW.name, NEW.description, NEW.href);
+END;
+
+-- rank links by name first, then by description and url.
+
+INSERT INTO links_fts(links_fts, rank) VALUES ('rank', 'bm25(10.0, 5.0, 1.0)');
+INSERT INTO links_fts(links_fts) VALUES ('rebuild');
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS tag_aliases
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    alias TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX tag_aliases_idx ON tag_aliases(user_id, alias);
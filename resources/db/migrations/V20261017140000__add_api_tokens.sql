@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS tokens
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    used_at DATETIME,
+    expires_at DATETIME
+);
+
+CREATE UNIQUE INDEX tokens_name_idx ON tokens(user_id, name);
+CREATE UNIQUE INDEX tokens_hash_idx ON tokens(hash);
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS sessions
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    access_hash TEXT NOT NULL,
+    refresh_hash TEXT NOT NULL,
+    access_expires_at DATETIME NOT NULL,
+    refresh_expires_at DATETIME NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    refreshed_at DATETIME
+);
+
+CREATE INDEX sessions_access_idx ON sessions(access_hash);
+CREATE INDEX sessions_refresh_idx ON sessions(refresh_hash);
+
+CREATE TABLE IF NOT EXISTS secrets
+(
+    name TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
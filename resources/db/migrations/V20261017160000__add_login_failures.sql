@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS login_failures
+(
+    kind TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    failed_at DATETIME NOT NULL,
+    locked_until DATETIME,
+    PRIMARY KEY (kind, subject)
+);
@@ -0,0 +1,8 @@
+-- API keys generated by previous versions were kept in plain text.
+-- they become hashed tokens of write scope, just like the ones generated now.
+
+INSERT OR IGNORE INTO tokens(user_id, name, hash, scope)
+SELECT id, 'legacy', sha256(api_key), 'write' FROM users
+WHERE api_key IS NOT NULL AND api_key != '';
+
+UPDATE users SET api_key = NULL;
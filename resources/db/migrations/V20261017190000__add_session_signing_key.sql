@@ -0,0 +1,3 @@
+-- key access tokens are signed with, generated once for every database.
+
+INSERT OR IGNORE INTO secrets(name, value) VALUES ('session', lower(hex(randomblob(32))));
@@ -0,0 +1,5 @@
+-- logins differing only by case are made unique by suffixing all but the oldest one with user id
+UPDATE users SET login = login || '-' || id
+ WHERE EXISTS (SELECT 1 FROM users u WHERE u.login = users.login COLLATE NOCASE AND u.id < users.id);
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_login_nocase_idx ON users(login COLLATE NOCASE);
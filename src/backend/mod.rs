@@ -0,0 +1,129 @@
+pub mod remote;
+
+use crate::db::{DBLookupType, DBResult};
+use crate::vault::auth::Authentication;
+use crate::vault::facets::Facets;
+use crate::vault::import::{ConflictPolicy, ImportFormat, ImportSummary};
+use crate::vault::link::{Link, Version};
+use crate::vault::search::cursor::Cursor;
+use crate::vault::stored_query::{self, StoredQuery};
+use crate::vault::Vault;
+
+/// Links storage commands are run against. Either a local database ([`Vault`]) or
+/// a remote linkify server ([`remote::Remote`]).
+pub trait Backend {
+    fn add_link(&self, auth: &Option<Authentication>, link: Link) -> DBResult<Version>;
+    fn del_link(&self, auth: &Option<Authentication>, href: &str) -> DBResult<Option<Link>>;
+    fn query_page(
+        &self,
+        auth: &Option<Authentication>,
+        query: &str,
+        version: Version,
+        limit: u16,
+        cursor: Option<Cursor>,
+    ) -> DBResult<(Vec<Link>, Version, Option<Cursor>)>;
+    fn query_facets(
+        &self,
+        auth: &Option<Authentication>,
+        query: &str,
+        version: Version,
+    ) -> DBResult<Facets>;
+    /// Imports links (and stored queries) from the contents of a file in given format (detected if not provided).
+    fn import_file(
+        &self,
+        auth: &Option<Authentication>,
+        contents: &str,
+        format: Option<ImportFormat>,
+        policy: ConflictPolicy,
+        dry_run: bool,
+    ) -> DBResult<ImportSummary>;
+    fn find_queries(
+        &self,
+        auth: &Option<Authentication>,
+        name: Option<&str>,
+        lookup_type: DBLookupType,
+    ) -> DBResult<Vec<StoredQuery>>;
+    fn store_query(&self, auth: &Option<Authentication>, name: &str, query: &str) -> DBResult<i64>;
+    fn del_query(&self, auth: &Option<Authentication>, name: &str)
+        -> DBResult<Option<StoredQuery>>;
+    fn rename_query(
+        &self,
+        auth: &Option<Authentication>,
+        from: &str,
+        to: &str,
+    ) -> DBResult<Option<StoredQuery>>;
+    fn expand_query(&self, auth: &Option<Authentication>, query: &str) -> DBResult<String> {
+        stored_query::expand(query, |name| {
+            self.find_queries(auth, Some(name), DBLookupType::Exact)
+        })
+    }
+}
+
+impl Backend for Vault {
+    fn add_link(&self, auth: &Option<Authentication>, link: Link) -> DBResult<Version> {
+        Vault::add_link(self, auth, link)
+    }
+    fn del_link(&self, auth: &Option<Authentication>, href: &str) -> DBResult<Option<Link>> {
+        Vault::del_link(self, auth, href)
+    }
+    fn query_page(
+        &self,
+        auth: &Option<Authentication>,
+        query: &str,
+        version: Version,
+        limit: u16,
+        cursor: Option<Cursor>,
+    ) -> DBResult<(Vec<Link>, Version, Option<Cursor>)> {
+        Vault::query_page(self, auth, query, version, limit, cursor)
+    }
+    fn query_facets(
+        &self,
+        auth: &Option<Authentication>,
+        query: &str,
+        version: Version,
+    ) -> DBResult<Facets> {
+        Vault::query_facets(self, auth, query, version)
+    }
+    fn import_file(
+        &self,
+        auth: &Option<Authentication>,
+        contents: &str,
+        format: Option<ImportFormat>,
+        policy: ConflictPolicy,
+        dry_run: bool,
+    ) -> DBResult<ImportSummary> {
+        Vault::import_file(self, auth, contents, format, policy, dry_run)
+    }
+    fn find_queries(
+        &self,
+        auth: &Option<Authentication>,
+        name: Option<&str>,
+        lookup_type: DBLookupType,
+    ) -> DBResult<Vec<StoredQuery>> {
+        Vault::find_queries(self, auth, name, lookup_type)
+    }
+    fn store_query(&self, auth: &Option<Authentication>, name: &str, query: &str) -> DBResult<i64> {
+        Vault::store_query(self, auth, name.to_string(), query.to_string())
+    }
+    fn del_query(
+        &self,
+        auth: &Option<Authentication>,
+        name: &str,
+    ) -> DBResult<Option<StoredQuery>> {
+        match Vault::find_queries(self, auth, Some(name), DBLookupType::Exact)?.pop() {
+            Some(stored) => Vault::del_query(self, auth, stored.id.unwrap_or_default()),
+            None => Ok(None),
+        }
+    }
+    fn rename_query(
+        &self,
+        auth: &Option<Authentication>,
+        from: &str,
+        to: &str,
+    ) -> DBResult<Option<StoredQuery>> {
+        Vault::rename_query(self, auth, from, to)
+    }
+    fn expand_query(&self, auth: &Option<Authentication>, query: &str) -> DBResult<String> {
+        Vault::expand_query(self, auth, query)
+    }
+}
@@ -0,0 +1,420 @@
+use crate::backend::Backend;
+use crate::db::DBError::{BadCursor, BadImport, BadQuery, Remote as RemoteError};
+use crate::db::DBError::{Unauthenticated, UnknownQuery, UnknownUser};
+use crate::db::{DBLookupType, DBResult};
+use crate::vault::auth::Authentication;
+use crate::vault::facets::Facets;
+use crate::vault::import::{ConflictPolicy, ImportFormat, ImportSummary};
+use crate::vault::link::{Link, Version};
+use crate::vault::search::{self, cursor::Cursor};
+use crate::vault::stored_query::StoredQuery;
+
+use miniserde::{json, Deserialize, Serialize};
+use std::time::Duration;
+use url::Url;
+
+const TIMEOUT_SECS: u64 = 30;
+
+/// Status and body of server response, whatever the status is.
+#[derive(Debug, PartialEq)]
+struct HttpResponse {
+    status: u16,
+    body: String,
+}
+
+/// Page of links, as returned by `GET /links` and `GET /search`.
+#[derive(Deserialize, Debug)]
+struct LinksPage {
+    version: i32,
+    links: Vec<Link>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FacetsPage {
+    facets: Facets,
+}
+
+#[derive(Serialize, Debug)]
+struct LinksRequest {
+    version: i32,
+    links: Vec<LinkData>,
+}
+
+#[derive(Serialize, Debug)]
+struct QueryRequest<'a> {
+    name: &'a str,
+    query: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct QueryRenameRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct LinkData {
+    href: String,
+    name: String,
+    description: String,
+    tags: Option<String>,
+    flags: Option<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    read_at: Option<String>,
+}
+
+impl From<Link> for LinkData {
+    fn from(link: Link) -> Self {
+        let flags: Vec<_> = [
+            (link.toread, "toread"),
+            (link.shared, "shared"),
+            (link.favourite, "favourite"),
+        ]
+        .iter()
+        .filter(|(is_set, _)| *is_set)
+        .map(|(_, flag)| *flag)
+        .collect();
+
+        LinkData {
+            href: link.href,
+            name: link.name,
+            description: link.description.unwrap_or_default(),
+            tags: link.tags.map(|t| t.join(",")),
+            flags: Some(flags.join(",")),
+            created_at: Some(link.created_at).filter(|c| !c.is_empty()),
+            updated_at: link.updated_at,
+            read_at: link.read_at,
+        }
+    }
+}
+
+/// Linkify server talked to over its HTTP(S) API, authenticated with user's API key.
+pub struct Remote {
+    url: Url,
+    agent: ureq::Agent,
+}
+
+impl Remote {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|e| format!("Invalid server url ({}).", e))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(format!(
+                "Unsupported server url ({}), only http:// and https:// are supported.",
+                url
+            ));
+        }
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(TIMEOUT_SECS))
+            .build();
+        Ok(Remote { url, agent })
+    }
+    /// Url of API endpoint. Server might be available under some path, like
+    /// `http://example.com/linkify`, which is preserved.
+    fn url(&self, path: &str, params: &[(&str, &str)]) -> Url {
+        let mut url = self.url.clone();
+        url.set_path(&format!(
+            "{}{}",
+            self.url.path().trim_end_matches('/'),
+            path
+        ));
+        url.set_query(None);
+        if !params.is_empty() {
+            url.query_pairs_mut().extend_pairs(params);
+        }
+        url
+    }
+    /// Sends a request, returning response of any status.
+    fn request(
+        &self,
+        auth: &Option<Authentication>,
+        method: &str,
+        url: &Url,
+        body: Option<(&str, &str)>,
+    ) -> DBResult<HttpResponse> {
+        let token = match auth {
+            Some(Authentication::Token(key)) => key.as_str(),
+            _ => return Err(Unauthenticated),
+        };
+        let request = self
+            .agent
+            .request(method, url.as_str())
+            .set("Authorization", &format!("Bearer {}", token));
+        let result = match body {
+            Some((content_type, body)) => {
+                request.set("Content-Type", content_type).send_string(body)
+            }
+            None => request.call(),
+        };
+
+        // responses of unsuccessful status are errors to ureq, but not necessarily to us.
+
+        let response = match result {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(RemoteError(format!("{} ({})", e, self.url))),
+        };
+        let status = response.status();
+        response
+            .into_string()
+            .map(|body| HttpResponse { status, body })
+            .map_err(|e| RemoteError(format!("{} ({})", e, self.url)))
+    }
+    /// Sends a request, failing unless response status is successful.
+    fn send(
+        &self,
+        auth: &Option<Authentication>,
+        method: &str,
+        url: Url,
+        body: Option<(&str, &str)>,
+    ) -> DBResult<HttpResponse> {
+        self.request(auth, method, &url, body)
+            .and_then(|response| check(&url, response))
+    }
+    fn fetch<T: Deserialize>(
+        &self,
+        auth: &Option<Authentication>,
+        path: &str,
+        params: &[(&str, &str)],
+    ) -> DBResult<T> {
+        let response = self.send(auth, "GET", self.url(path, params), None)?;
+        json::from_str(&response.body)
+            .map_err(|_| RemoteError(format!("Malformed response ({})", path)))
+    }
+    /// Looks up a link by its url, along with latest version of user's links.
+    fn find_link(
+        &self,
+        auth: &Option<Authentication>,
+        href: &str,
+    ) -> DBResult<(Option<Link>, Version)> {
+        let page: LinksPage = self.fetch(
+            auth,
+            "/links",
+            &[("q", href), ("exact", "true"), ("limit", "1")],
+        )?;
+        Ok((page.links.into_iter().next(), Version::new(page.version)))
+    }
+    fn find_query(
+        &self,
+        auth: &Option<Authentication>,
+        name: &str,
+    ) -> DBResult<Option<StoredQuery>> {
+        Ok(self
+            .find_queries(auth, Some(name), DBLookupType::Exact)?
+            .pop())
+    }
+}
+
+/// Turns unsuccessful response into an error.
+fn check(url: &Url, response: HttpResponse) -> DBResult<HttpResponse> {
+    match response.status {
+        200..=299 => Ok(response),
+        401 => Err(Unauthenticated),
+        403 if response.body.trim().is_empty() => Err(UnknownUser),
+        status => Err(RemoteError(format!(
+            "{} {} {}",
+            status,
+            url.path(),
+            response.body.trim()
+        ))),
+    }
+}
+
+impl Backend for Remote {
+    fn add_link(&self, auth: &Option<Authentication>, link: Link) -> DBResult<Version> {
+        // just like locally, link is stored on top of the latest version
+        // so that it doesn't conflict with anything stored before.
+
+        let version = self.find_link(auth, &link.href)?.1.bump();
+        let request = LinksRequest {
+            version: version.offset(),
+            links: vec![LinkData::from(link)],
+        };
+        let body = json::to_string(&request);
+        self.send(
+            auth,
+            "POST",
+            self.url("/links", &[]),
+            Some(("application/json", &body)),
+        )?;
+        Ok(version)
+    }
+    fn del_link(&self, auth: &Option<Authentication>, href: &str) -> DBResult<Option<Link>> {
+        let page: LinksPage = self.fetch(auth, "/links", &[("q", href), ("exact", "true")])?;
+
+        // links shared by other users are found too, but only the own one may be deleted.
+
+        for link in page.links {
+            let url = self.url(&format!("/links/{}", link.id.unwrap_or_default()), &[]);
+            let response = self.request(auth, "DELETE", &url, None)?;
+            if response.status != 404 {
+                check(&url, response)?;
+                return Ok(Some(link.set_deleted(true)));
+            }
+        }
+        Ok(None)
+    }
+    fn query_page(
+        &self,
+        auth: &Option<Authentication>,
+        query: &str,
+        version: Version,
+        limit: u16,
+        cursor: Option<Cursor>,
+    ) -> DBResult<(Vec<Link>, Version, Option<Cursor>)> {
+        // query is validated upfront to report errors the same way as local database does.
+
+        search::parse(query).map_err(BadQuery)?;
+        let cursor = cursor.map(|c| c.encode()).unwrap_or_default();
+        let page: LinksPage = self.fetch(
+            auth,
+            "/search",
+            &[
+                ("q", query),
+                ("version", &version.offset().to_string()),
+                ("limit", &limit.to_string()),
+                ("cursor", &cursor),
+            ],
+        )?;
+        let next = match page.next_cursor {
+            Some(c) => Some(Cursor::decode(&c).ok_or(BadCursor)?),
+            None => None,
+        };
+        Ok((page.links, Version::new(page.version), next))
+    }
+    fn query_facets(
+        &self,
+        auth: &Option<Authentication>,
+        query: &str,
+        version: Version,
+    ) -> DBResult<Facets> {
+        search::parse(query).map_err(BadQuery)?;
+        let page: FacetsPage = self.fetch(
+            auth,
+            "/search",
+            &[
+                ("q", query),
+                ("version", &version.offset().to_string()),
+                ("limit", "1"),
+                ("facets", "true"),
+            ],
+        )?;
+        Ok(page.facets)
+    }
+    fn import_file(
+        &self,
+        auth: &Option<Authentication>,
+        contents: &str,
+        format: Option<ImportFormat>,
+        policy: ConflictPolicy,
+        dry_run: bool,
+    ) -> DBResult<ImportSummary> {
+        let (policy, dry_run) = (policy.to_string(), dry_run.to_string());
+        let format = format.map(|f| f.to_string());
+        let mut params = vec![
+            ("on_conflict", policy.as_str()),
+            ("dry_run", dry_run.as_str()),
+        ];
+        if let Some(format) = &format {
+            params.push(("format", format));
+        }
+        let url = self.url("/import", &params);
+
+        // file which couldn't be read is reported as bad request
+        let response = match self.request(auth, "POST", &url, Some(("text/plain", contents)))? {
+            r if r.status == 400 => return Err(BadImport(r.body.trim().to_string())),
+            r => check(&url, r)?,
+        };
+        json::from_str(&response.body)
+            .map_err(|_| RemoteError("Malformed response (/import)".into()))
+    }
+    fn find_queries(
+        &self,
+        auth: &Option<Authentication>,
+        name: Option<&str>,
+        lookup_type: DBLookupType,
+    ) -> DBResult<Vec<StoredQuery>> {
+        let exact = match lookup_type {
+            DBLookupType::Exact => "true",
+            DBLookupType::Patterned => "false",
+        };
+        match name {
+            Some(name) => self.fetch(auth, "/queries", &[("q", name), ("exact", exact)]),
+            None => self.fetch(auth, "/queries", &[]),
+        }
+    }
+    fn store_query(&self, auth: &Option<Authentication>, name: &str, query: &str) -> DBResult<i64> {
+        search::parse(query).map_err(BadQuery)?;
+        let body = json::to_string(&QueryRequest { name, query });
+        self.send(
+            auth,
+            "POST",
+            self.url("/queries", &[]),
+            Some(("application/json", &body)),
+        )?;
+
+        // server doesn't respond with identifier of stored query, it needs to be looked up.
+        match self.find_query(auth, name)? {
+            Some(stored) => Ok(stored.id.unwrap_or_default()),
+            None => Err(UnknownQuery(name.to_string())),
+        }
+    }
+    fn del_query(
+        &self,
+        auth: &Option<Authentication>,
+        name: &str,
+    ) -> DBResult<Option<StoredQuery>> {
+        match self.find_query(auth, name)? {
+            Some(stored) => {
+                let path = format!("/queries/{}", stored.id.unwrap_or_default());
+                self.send(auth, "DELETE", self.url(&path, &[]), None)?;
+                Ok(Some(stored))
+            }
+            None => Ok(None),
+        }
+    }
+    fn rename_query(
+        &self,
+        auth: &Option<Authentication>,
+        from: &str,
+        to: &str,
+    ) -> DBResult<Option<StoredQuery>> {
+        let url = self.url("/queries/rename", &[]);
+        let body = json::to_string(&QueryRenameRequest { from, to });
+        match self.request(auth, "POST", &url, Some(("application/json", &body)))? {
+            r if r.status == 404 => Ok(None),
+            r => {
+                let response = check(&url, r)?;
+                json::from_str(&response.body)
+                    .map(Some)
+                    .map_err(|_| RemoteError("Malformed response (/queries/rename)".into()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_remote {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("http://localhost:8001", "http://localhost:8001/links?q=a+b")]
+    #[case("http://localhost/linkify/", "http://localhost/linkify/links?q=a+b")]
+    #[case("http://localhost/linkify?x=y", "http://localhost/linkify/links?q=a+b")]
+    #[case(
+        "https://linkify.example.com",
+        "https://linkify.example.com/links?q=a+b"
+    )]
+    fn test_url(#[case] server: &str, #[case] expected: &str) {
+        let remote = Remote::new(server).unwrap();
+        assert_eq!(expected, remote.url("/links", &[("q", "a b")]).as_str());
+    }
+
+    #[rstest]
+    #[case("ftp://localhost")]
+    #[case("localhost:8001")]
+    fn test_unsupported_url(#[case] server: &str) {
+        assert!(Remote::new(server).is_err());
+    }
+}
@@ -0,0 +1,586 @@
+use crate::db::DBLookupType;
+use crate::utils::truncate;
+use crate::vault::auth::Authentication;
+use crate::vault::link::{Link, LinkEdit, Version};
+use crate::vault::tags::Tag;
+use crate::vault::Vault;
+
+use colored::Colorize;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use terminal_size::{terminal_size, Height, Width};
+
+const SIDEBAR_WIDTH: usize = 24;
+const SIDEBAR_TAGS: u16 = 100;
+const LINKS_LIMIT: u16 = 500;
+const HELP: &str = "enter: open  tab: sidebar  ^r: toread  ^f: favourite  ^s: shared  \
+                    ^t: tags  ^d: delete  ^u: clear  esc: quit";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Backspace,
+    Tab,
+    Esc,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+}
+
+/// Decodes keys pressed out of bytes read from terminal in raw mode.
+fn decode(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let (key, len) = match bytes[i..] {
+            [0x1b, b'[', b'A', ..] | [0x1b, b'O', b'A', ..] => (Some(Key::Up), 3),
+            [0x1b, b'[', b'B', ..] | [0x1b, b'O', b'B', ..] => (Some(Key::Down), 3),
+            [0x1b, b'[', b'5', b'~', ..] => (Some(Key::PageUp), 4),
+            [0x1b, b'[', b'6', b'~', ..] => (Some(Key::PageDown), 4),
+            // other sequences (like left/right arrows) are not handled
+            [0x1b, b'[', ..] => {
+                let len = bytes[i + 2..]
+                    .iter()
+                    .position(|b| (0x40..=0x7e).contains(b))
+                    .map_or(bytes.len() - i, |p| p + 3);
+                (None, len)
+            }
+            [0x1b, ..] => (Some(Key::Esc), 1),
+            [b'\r', ..] | [b'\n', ..] => (Some(Key::Enter), 1),
+            [0x7f, ..] | [0x08, ..] => (Some(Key::Backspace), 1),
+            [b'\t', ..] => (Some(Key::Tab), 1),
+            [b @ 0x01..=0x1a, ..] => (Some(Key::Ctrl((b'a' + b - 1) as char)), 1),
+            [b, ..] => {
+                let len = match b {
+                    0xf0..=0xf7 => 4,
+                    0xe0..=0xef => 3,
+                    0xc0..=0xdf => 2,
+                    _ => 1,
+                };
+                let len = len.min(bytes.len() - i);
+                let key = std::str::from_utf8(&bytes[i..i + len])
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                    .filter(|c| !c.is_control())
+                    .map(Key::Char);
+                (key, len)
+            }
+            [] => break,
+        };
+        keys.extend(key);
+        i += len;
+    }
+    keys
+}
+
+/// Turns edited list of tags into changes of link's tags, in `tag`/`-tag` notation.
+fn tag_changes(current: &[Tag], edited: &str) -> Vec<Tag> {
+    let edited: Vec<_> = edited
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .collect();
+    let removed = current
+        .iter()
+        .filter(|t| !edited.contains(&t.as_str()))
+        .map(|t| format!("-{}", t));
+    let added = edited
+        .iter()
+        .filter(|t| !current.iter().any(|c| c == *t))
+        .map(|t| t.to_string());
+
+    removed.chain(added).collect()
+}
+
+/// Terminal switched into raw mode and alternate screen. Restored back once dropped.
+struct Terminal {
+    original: libc::termios,
+}
+
+impl Terminal {
+    fn open() -> io::Result<Self> {
+        let original = unsafe {
+            let mut original: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+
+            // reads return after 100ms even if nothing was pressed
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 1;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            original
+        };
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[?1049h\x1b[?25l")?;
+        stdout.flush()?;
+        Ok(Terminal { original })
+    }
+    fn size(&self) -> (usize, usize) {
+        terminal_size()
+            .map(|(Width(w), Height(h))| (w as usize, h as usize))
+            .unwrap_or((80, 24))
+    }
+    fn read_keys(&self) -> io::Result<Vec<Key>> {
+        let mut buf = [0u8; 64];
+        let n = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+            )
+        };
+        match n {
+            n if n >= 0 => Ok(decode(&buf[..n as usize])),
+            _ => match io::Error::last_os_error() {
+                e if e.kind() == io::ErrorKind::Interrupted => Ok(Vec::new()),
+                e => Err(e),
+            },
+        }
+    }
+    fn draw(&self, lines: &[String]) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[H{}\x1b[J", lines.join("\x1b[K\r\n"))?;
+        stdout.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &self.original);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Focus {
+    Links,
+    Sidebar,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Prompt {
+    Tags(String),
+    Delete,
+}
+
+/// State of the browser: current query, matching links and sidebar with
+/// stored queries (as `@name`) followed by recently used tags.
+struct Browser<'a> {
+    vault: &'a Vault,
+    auth: &'a Option<Authentication>,
+    query: String,
+    links: Vec<Link>,
+    selected: usize,
+    sidebar: Vec<String>,
+    sidebar_selected: usize,
+    focus: Focus,
+    prompt: Option<Prompt>,
+    status: Option<String>,
+    page: usize,
+    quit: bool,
+}
+
+impl<'a> Browser<'a> {
+    fn new(vault: &'a Vault, auth: &'a Option<Authentication>, query: &str) -> Self {
+        let mut browser = Browser {
+            vault,
+            auth,
+            query: query.to_string(),
+            links: Vec::new(),
+            selected: 0,
+            sidebar: Vec::new(),
+            sidebar_selected: 0,
+            focus: Focus::Links,
+            prompt: None,
+            status: None,
+            page: 1,
+            quit: false,
+        };
+        browser.refresh_sidebar();
+        browser.search();
+        browser
+    }
+    fn search(&mut self) {
+        let links = self
+            .vault
+            .expand_query(self.auth, &self.query)
+            .and_then(|q| {
+                self.vault
+                    .query_links(self.auth, q, Version::unknown(), Some(LINKS_LIMIT))
+            });
+
+        // query being typed is often incomplete, previous links stay until it gets valid.
+        match links {
+            Ok((links, _)) => {
+                self.links = links;
+                self.selected = self.selected.min(self.links.len().saturating_sub(1));
+                self.status = None;
+            }
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+    fn refresh_sidebar(&mut self) {
+        let queries = self
+            .vault
+            .find_queries(self.auth, Some(""), DBLookupType::Patterned);
+        let tags = self
+            .vault
+            .recent_tags(self.auth, None, None, Some(SIDEBAR_TAGS));
+
+        match queries.and_then(|q| tags.map(|t| (q, t))) {
+            Ok((queries, tags)) => {
+                self.sidebar = queries
+                    .into_iter()
+                    .map(|q| format!("@{}", q.name))
+                    .chain(tags)
+                    .collect();
+                self.sidebar_selected = self
+                    .sidebar_selected
+                    .min(self.sidebar.len().saturating_sub(1));
+            }
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+    fn handle(&mut self, key: Key) {
+        match self.prompt.take() {
+            Some(prompt) => self.handle_prompt(prompt, key),
+            None => self.handle_key(key),
+        }
+    }
+    fn handle_prompt(&mut self, prompt: Prompt, key: Key) {
+        match (prompt, key) {
+            (Prompt::Tags(mut input), Key::Char(c)) => {
+                input.push(c);
+                self.prompt = Some(Prompt::Tags(input));
+            }
+            (Prompt::Tags(mut input), Key::Backspace) => {
+                input.pop();
+                self.prompt = Some(Prompt::Tags(input));
+            }
+            (Prompt::Tags(input), Key::Enter) => {
+                let current = self
+                    .selected_link()
+                    .and_then(|l| l.tags.clone())
+                    .unwrap_or_default();
+                let changes = tag_changes(&current, &input);
+                if changes.is_empty() {
+                    self.status = Some("Nothing to change.".to_string());
+                } else {
+                    self.edit(LinkEdit::default().set_tags(changes));
+                    self.refresh_sidebar();
+                }
+            }
+            (Prompt::Delete, Key::Char('y')) => self.delete(),
+            (Prompt::Tags(input), k) if k != Key::Esc && k != Key::Ctrl('c') => {
+                self.prompt = Some(Prompt::Tags(input))
+            }
+            _ => self.status = None,
+        }
+    }
+    fn handle_key(&mut self, key: Key) {
+        let rows = self.page;
+        match key {
+            Key::Esc | Key::Ctrl('c') => self.quit = true,
+            Key::Tab => {
+                self.focus = match self.focus {
+                    Focus::Links => Focus::Sidebar,
+                    Focus::Sidebar => Focus::Links,
+                }
+            }
+            Key::Up => self.move_by(-1),
+            Key::Down => self.move_by(1),
+            Key::PageUp => self.move_by(-(rows as isize)),
+            Key::PageDown => self.move_by(rows as isize),
+            Key::Enter if self.focus == Focus::Sidebar => self.pick(),
+            Key::Enter => self.open(),
+            Key::Ctrl('r') => self.toggle("toread", |l| l.toread),
+            Key::Ctrl('f') => self.toggle("favourite", |l| l.favourite),
+            Key::Ctrl('s') => self.toggle("shared", |l| l.shared),
+            Key::Ctrl('t') => {
+                if let Some(link) = self.selected_link() {
+                    let tags = link.tags.clone().unwrap_or_default().join(" ");
+                    self.prompt = Some(Prompt::Tags(tags));
+                }
+            }
+            Key::Ctrl('d') if self.selected_link().is_some() => self.prompt = Some(Prompt::Delete),
+            Key::Ctrl('u') => {
+                self.query.clear();
+                self.search();
+            }
+            Key::Char(c) => {
+                self.query.push(c);
+                self.focus = Focus::Links;
+                self.search();
+            }
+            Key::Backspace => {
+                self.query.pop();
+                self.search();
+            }
+            _ => (),
+        }
+    }
+    fn move_by(&mut self, delta: isize) {
+        let (selected, len) = match self.focus {
+            Focus::Links => (&mut self.selected, self.links.len()),
+            Focus::Sidebar => (&mut self.sidebar_selected, self.sidebar.len()),
+        };
+        let max = len.saturating_sub(1) as isize;
+        *selected = (*selected as isize + delta).max(0).min(max) as usize;
+    }
+    fn selected_link(&self) -> Option<&Link> {
+        self.links.get(self.selected)
+    }
+    /// Picks selected sidebar item. Stored query replaces current query, tag narrows it down.
+    fn pick(&mut self) {
+        if let Some(item) = self.sidebar.get(self.sidebar_selected) {
+            if item.starts_with('@') {
+                self.query = item.clone();
+            } else {
+                let query = format!("{} tags:{}", self.query.trim_end(), item);
+                self.query = query.trim_start().to_string();
+            }
+            self.focus = Focus::Links;
+            self.selected = 0;
+            self.search();
+        }
+    }
+    fn toggle<F: Fn(&Link) -> bool>(&mut self, flag: &str, is_set: F) {
+        if let Some(link) = self.selected_link() {
+            let flag = if is_set(link) {
+                format!("-{}", flag)
+            } else {
+                flag.to_string()
+            };
+            if let Ok(edit) = LinkEdit::default().set_flags(&[flag]) {
+                self.edit(edit);
+            }
+        }
+    }
+    fn edit(&mut self, edit: LinkEdit) {
+        let href = match self.selected_link() {
+            Some(link) => link.href.clone(),
+            None => return,
+        };
+        match self.vault.edit_link(self.auth, &href, &edit) {
+            Ok(Some(link)) => {
+                self.links[self.selected] = link;
+                self.status = Some(format!("Updated ({}).", href));
+            }
+            Ok(None) => self.status = Some("No such a link found.".to_string()),
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+    fn delete(&mut self) {
+        let href = match self.selected_link() {
+            Some(link) => link.href.clone(),
+            None => return,
+        };
+        match self.vault.del_link(self.auth, &href) {
+            Ok(Some(_)) => {
+                self.links.remove(self.selected);
+                self.selected = self.selected.min(self.links.len().saturating_sub(1));
+                self.status = Some(format!("Deleted ({}).", href));
+            }
+            Ok(None) => self.status = Some("No such a link found.".to_string()),
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+    fn open(&mut self) {
+        if let Some(link) = self.selected_link() {
+            let href = link.href.clone();
+            self.status = Some(match open_browser(&href) {
+                Ok(_) => format!("Opened ({}).", href),
+                Err(e) => format!("Cannot open browser ({}).", e),
+            });
+        }
+    }
+    fn render(&mut self, width: usize, height: usize) -> Vec<String> {
+        let rows = height.saturating_sub(3);
+        let links_width = width.saturating_sub(SIDEBAR_WIDTH + 3);
+        let links_offset = (self.selected + 1).saturating_sub(rows);
+        let sidebar_offset = (self.sidebar_selected + 1).saturating_sub(rows);
+        self.page = rows.max(1);
+
+        let mut lines = Vec::with_capacity(height);
+        lines.push(format!(
+            "{} {}{} {}",
+            "Search:".bold(),
+            self.query,
+            " ".reversed(),
+            format!("({} links)", self.links.len()).dimmed()
+        ));
+        lines.push("─".repeat(width).dimmed().to_string());
+
+        for row in 0..rows {
+            let item = self
+                .sidebar
+                .get(sidebar_offset + row)
+                .map(|item| {
+                    let cell = format!(
+                        "{:<w$}",
+                        truncate(item, SIDEBAR_WIDTH as i16),
+                        w = SIDEBAR_WIDTH
+                    );
+                    if sidebar_offset + row != self.sidebar_selected {
+                        cell.normal()
+                    } else if self.focus == Focus::Sidebar {
+                        cell.reversed()
+                    } else {
+                        cell.bold()
+                    }
+                })
+                .unwrap_or_else(|| " ".repeat(SIDEBAR_WIDTH).normal());
+            let link = self
+                .links
+                .get(links_offset + row)
+                .map(|link| {
+                    let selected = links_offset + row == self.selected;
+                    render_link(link, links_width, selected && self.focus == Focus::Links)
+                })
+                .unwrap_or_default();
+            lines.push(format!("{} {} {}", item, "│".dimmed(), link));
+        }
+        lines.push(match &self.prompt {
+            Some(Prompt::Tags(input)) => format!("{} {}{}", "Tags:".bold(), input, " ".reversed()),
+            Some(Prompt::Delete) => format!(
+                "{} {}? (y/n)",
+                "Delete".bold(),
+                self.selected_link()
+                    .map(|l| l.href.as_str())
+                    .unwrap_or_default()
+            ),
+            None => match &self.status {
+                Some(status) => truncate(status, width as i16).yellow().to_string(),
+                None => truncate(HELP, width as i16).dimmed().to_string(),
+            },
+        });
+        lines
+    }
+}
+
+/// Renders a link as `flags href | name #tags` cut down to given width.
+fn render_link(link: &Link, width: usize, selected: bool) -> String {
+    let flags: String = [
+        (link.toread, 'r'),
+        (link.favourite, 'f'),
+        (link.shared, 's'),
+    ]
+    .iter()
+    .map(|(is_set, flag)| if *is_set { *flag } else { '-' })
+    .collect();
+    let tags: String = link
+        .tags
+        .iter()
+        .flatten()
+        .map(|t| format!(" #{}", t))
+        .collect();
+    let head = format!("{} {}", flags, link.href);
+    let head = truncate(&head, width as i16);
+    let rest = format!(" | {}{}", link.name, tags);
+    let rest = truncate(&rest, (width - head.chars().count()) as i16);
+
+    if selected {
+        format!("{:<w$}", format!("{}{}", head, rest), w = width)
+            .reversed()
+            .to_string()
+    } else {
+        format!("{}{}", head, rest.blue())
+    }
+}
+
+/// Opens link with a program pointed by `$BROWSER` or with a default opener of the system.
+fn open_browser(href: &str) -> io::Result<()> {
+    let browser = env::var("BROWSER")
+        .ok()
+        .and_then(|b| b.split(':').next().map(String::from))
+        .filter(|b| !b.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "macos") {
+                "open".to_string()
+            } else {
+                "xdg-open".to_string()
+            }
+        });
+    let mut command = browser.split_whitespace();
+    Command::new(command.next().unwrap_or_default())
+        .args(command)
+        .arg(href)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Runs full-screen browser of links matching given (initial) query.
+pub fn browse(vault: &Vault, auth: &Option<Authentication>, query: &str) -> Result<(), String> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err("Terminal expected.".to_string());
+    }
+    vault.authenticate_user(auth).map_err(|e| e.to_string())?;
+
+    let terminal = Terminal::open().map_err(|e| e.to_string())?;
+    let mut browser = Browser::new(vault, auth, query);
+    let mut size = None;
+    let mut redraw = true;
+
+    while !browser.quit {
+        let current = terminal.size();
+        if redraw || size != Some(current) {
+            terminal
+                .draw(&browser.render(current.0, current.1))
+                .map_err(|e| e.to_string())?;
+            size = Some(current);
+        }
+        let keys = terminal.read_keys().map_err(|e| e.to_string())?;
+        redraw = !keys.is_empty();
+        for key in keys {
+            browser.handle(key);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_browse {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(b"ab", vec![Key::Char('a'), Key::Char('b')])]
+    #[case("zł".as_bytes(), vec![Key::Char('z'), Key::Char('ł')])]
+    #[case(b"\x1b[A\x1b[B\x1bOA", vec![Key::Up, Key::Down, Key::Up])]
+    #[case(b"\x1b[5~\x1b[6~", vec![Key::PageUp, Key::PageDown])]
+    #[case(b"\x1b[C\x1b[1;5Dx", vec![Key::Char('x')])]
+    #[case(b"\x1b", vec![Key::Esc])]
+    #[case(b"\r\t\x7f\x04", vec![Key::Enter, Key::Tab, Key::Backspace, Key::Ctrl('d')])]
+    fn test_decode(#[case] bytes: &[u8], #[case] expected: Vec<Key>) {
+        assert_eq!(expected, decode(bytes));
+    }
+
+    #[rstest]
+    #[case(&["rust", "async"], "rust tokio", vec!["-async", "tokio"])]
+    #[case(&["rust"], "rust, async", vec!["async"])]
+    #[case(&["rust"], "  ", vec!["-rust"])]
+    #[case(&["rust"], "rust", vec![])]
+    fn test_tag_changes(
+        #[case] current: &[&str],
+        #[case] edited: &str,
+        #[case] expected: Vec<&str>,
+    ) {
+        let current: Vec<_> = current.iter().map(|t| t.to_string()).collect();
+        assert_eq!(expected, tag_changes(&current, edited));
+    }
+}
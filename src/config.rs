@@ -5,6 +5,8 @@ use std::env;
 pub enum Env {
     Database,
     ApiKey,
+    Server,
+    TrustedProxies,
 }
 
 pub struct Config {
@@ -14,7 +16,7 @@ pub struct Config {
 impl Config {
     pub fn default() -> Self {
         let mut config = Config {
-            values: HashMap::<Env, String>::with_capacity(3),
+            values: HashMap::<Env, String>::with_capacity(4),
         };
         if let Ok(path) = env::var("LINKIFY_DB_PATH") {
             config.values.insert(Env::Database, path);
@@ -22,6 +24,12 @@ impl Config {
         if let Ok(apikey) = env::var("LINKIFY_API_KEY") {
             config.values.insert(Env::ApiKey, apikey);
         }
+        if let Ok(server) = env::var("LINKIFY_SERVER") {
+            config.values.insert(Env::Server, server);
+        }
+        if let Ok(proxies) = env::var("LINKIFY_TRUSTED_PROXIES") {
+            config.values.insert(Env::TrustedProxies, proxies);
+        }
         config
     }
     pub fn get(&self, key: Env) -> Option<&str> {
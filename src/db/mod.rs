@@ -1,6 +1,8 @@
 pub mod query;
 
-use super::utils::{every, path, some};
+use super::utils::{every, host, path, some};
+use super::vault::search::SearchError;
+use super::vault::token::digest;
 
 use failure::Fail;
 use log::debug;
@@ -26,6 +28,45 @@ pub enum DBError {
 
     #[fail(display = "Incorrect version")]
     BadVersion,
+
+    #[fail(display = "Invalid query: {}", _0)]
+    BadQuery(SearchError),
+
+    #[fail(display = "Unknown stored query: {}", _0)]
+    UnknownQuery(String),
+
+    #[fail(display = "Stored query already exists: {}", _0)]
+    DuplicatedQuery(String),
+
+    #[fail(display = "User already exists: {}", _0)]
+    DuplicatedUser(String),
+
+    #[fail(display = "Token already exists: {}", _0)]
+    DuplicatedToken(String),
+
+    #[fail(display = "Insufficient token scope")]
+    Forbidden,
+
+    #[fail(display = "Admin role required")]
+    NotAdmin,
+
+    #[fail(display = "Too many failed login attempts, retry in {} seconds", _0)]
+    LockedOut(i64),
+
+    #[fail(display = "Invalid timestamp: {}", _0)]
+    BadTimestamp(String),
+
+    #[fail(display = "Invalid tag alias: {}", _0)]
+    BadAlias(String),
+
+    #[fail(display = "Invalid cursor")]
+    BadCursor,
+
+    #[fail(display = "Invalid imported file: {}", _0)]
+    BadImport(String),
+
+    #[fail(display = "Remote server error: {}", _0)]
+    Remote(String),
 }
 
 /// Lookup type for core entities, like users and links
@@ -54,6 +95,15 @@ fn add_functions(conn: &Connection) -> Result<(), DBError> {
             Ok(path(&url))
         },
     )?;
+    conn.create_scalar_function(
+        "host",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            let url = ctx.get::<String>(0)?;
+            Ok(host(&url))
+        },
+    )?;
     conn.create_scalar_function(
         "every",
         2,
@@ -74,6 +124,15 @@ fn add_functions(conn: &Connection) -> Result<(), DBError> {
             Ok(some(&elements, &expected))
         },
     )?;
+    conn.create_scalar_function(
+        "sha256",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            let value = ctx.get::<String>(0)?;
+            Ok(digest(&value))
+        },
+    )?;
     Ok(())
 }
 
@@ -4,10 +4,16 @@ use failure::_core::iter::FromIterator;
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{Row, ToSql};
+use std::borrow::Cow;
+
+enum Param<'a> {
+    Borrowed(&'a dyn ToSql),
+    Owned(Box<dyn ToSql>),
+}
 
 pub struct Query<'a> {
-    params: Vec<(&'a str, &'a dyn ToSql)>,
-    query: Vec<&'a str>,
+    params: Vec<(Cow<'a, str>, Param<'a>)>,
+    query: Vec<Cow<'a, str>>,
 }
 
 impl<'a> Query<'a> {
@@ -19,11 +25,11 @@ impl<'a> Query<'a> {
     }
     pub fn new_with_initial(initial_query: &'static str) -> Self {
         let mut q = Query::new();
-        q.query.push(initial_query);
+        q.query.push(initial_query.into());
         q
     }
     pub fn concat(&mut self, query_str: &'static str) -> &mut Self {
-        self.query.push(query_str);
+        self.query.push(query_str.into());
         self
     }
     pub fn concat_with_param(
@@ -31,18 +37,49 @@ impl<'a> Query<'a> {
         query_str: &'static str,
         query_param: (&'a str, &'a dyn ToSql),
     ) -> &mut Self {
-        self.params.push(query_param);
+        self.params
+            .push((query_param.0.into(), Param::Borrowed(query_param.1)));
         self.concat(query_str)
     }
+    /// Appends dynamically generated chunk of query, eg. compiled from parsed search query.
+    ///
+    /// Values referred by the chunk are expected to be [bound](Query::bind) beforehand.
+    pub fn concat_owned(&mut self, query_str: String) -> &mut Self {
+        self.query.push(query_str.into());
+        self
+    }
+    /// Binds a value to newly generated named parameter and returns the parameter name,
+    /// so it could be used within a chunk added by [`Query::concat_owned`].
+    pub fn bind<T: ToSql + 'static>(&mut self, value: T) -> String {
+        let name = format!(":p{}", self.params.len());
+        self.params
+            .push((name.clone().into(), Param::Owned(Box::new(value))));
+        name
+    }
     pub fn build(&self) -> String {
         self.query.join(" ")
     }
-    pub fn named_params(&self) -> &[(&str, &dyn ToSql)] {
-        self.params.as_slice()
+    pub fn named_params(&self) -> Vec<(&str, &dyn ToSql)> {
+        self.params
+            .iter()
+            .map(|(name, param)| match param {
+                Param::Borrowed(value) => (name.as_ref(), *value),
+                Param::Owned(value) => (name.as_ref(), value.as_ref()),
+            })
+            .collect()
     }
     pub fn patternize(arg: &str) -> String {
         format!("%{}%", arg)
     }
+    /// Turns whitespace separated words into FTS5 match expression, where every word
+    /// becomes a quoted prefix query, eg. `foo bar` gets translated into `"foo"* "bar"*`.
+    pub fn match_expression(arg: &str) -> String {
+        arg.split_whitespace()
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
     pub fn fetch_as<T, F>(
         &self,
         conn: PooledConnection<SqliteConnectionManager>,
@@ -52,7 +89,7 @@ impl<'a> Query<'a> {
         F: Fn(&Row) -> T,
     {
         let mut stmt = conn.prepare(&self.build())?;
-        let rows = stmt.query_map(self.named_params(), |row| Ok(f(row)))?;
+        let rows = stmt.query_map(self.named_params().as_slice(), |row| Ok(f(row)))?;
 
         Result::from_iter(rows).map_err(Into::into)
     }
@@ -1,20 +1,37 @@
+#![allow(non_local_definitions)]
+
+mod backend;
+mod browse;
 mod config;
 mod db;
+mod output;
 mod server;
 mod utils;
 mod vault;
 
+use backend::remote::Remote;
+use backend::Backend;
 use config::{Config, Env};
-use utils::{password, read_file, truncate};
+use db::DBError::BadImport;
+use db::{DBLookupType, DBResult};
+use output::{flatten_tags, print_records, FacetRecord, OutputFormat, Record};
+use server::proxy::TrustedProxies;
+use utils::{confirm, password, read_file, truncate};
 use vault::auth::Authentication;
-use vault::link::{Link, Version};
+use vault::bulk::BulkAction;
+use vault::export::ExportFormat;
+use vault::import::{ConflictPolicy, ImportFormat};
+use vault::link::{Link, LinkEdit, Version};
+use vault::tags::TagNode;
+use vault::token::Scope;
 use vault::Vault;
 
 use clap::{load_yaml, App, ArgMatches};
 use colored::Colorize;
-use db::DBLookupType;
-use miniserde::json;
+use log::LevelFilter;
 use simple_logger::SimpleLogger;
+use std::fs;
+use std::io::IsTerminal;
 use std::path::Path;
 use std::process::exit;
 use std::{thread, time};
@@ -22,6 +39,7 @@ use terminal_size::{terminal_size as ts, Width};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const RETRIES: i32 = 10;
+const REMOTE_COMMANDS: &[&str] = &["add", "del", "ls", "import", "queries"];
 
 fn ensure_db_exists(db: &str) {
     let mut retry = RETRIES;
@@ -44,12 +62,51 @@ fn ensure_db_exists(db: &str) {
 }
 
 fn main() {
-    SimpleLogger::new().init().unwrap();
-
     let yaml = load_yaml!("cli.yml");
     let config = Config::default();
     let matches = App::from(yaml).get_matches();
     let is_server = matches.is_present("server");
+
+    let is_tty = std::io::stdout().is_terminal();
+
+    // no colours (nor logs, which go to stdout) when piped into another program
+    // or redirected to a file, so that listed records can be processed further.
+    // logs would also break the full-screen browser.
+    if !is_tty {
+        colored::control::set_override(false);
+    }
+    if !is_server && (!is_tty || matches.subcommand_name() == Some("browse")) {
+        SimpleLogger::new()
+            .with_level(LevelFilter::Off)
+            .init()
+            .unwrap();
+    } else {
+        SimpleLogger::new().init().unwrap();
+    }
+
+    // links might be managed on a remote server instead of local database. only the
+    // commands backed by server's HTTP API are available then.
+    let remote = matches
+        .value_of("server-url")
+        .or_else(|| config.get(Env::Server))
+        .map(String::from);
+    if let (Some(url), false) = (remote, is_server) {
+        let remote = Remote::new(&url).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            exit(-1);
+        });
+        match matches.subcommand_name() {
+            Some(cmd) if REMOTE_COMMANDS.contains(&cmd) => {
+                process_links_command(config, &remote, &matches)
+            }
+            Some(cmd) => {
+                eprintln!("Command not available with remote server ({}).", cmd);
+                exit(-1);
+            }
+            None => (),
+        }
+        return;
+    }
     let db = matches
         .value_of("database")
         .or_else(|| config.get(Env::Database))
@@ -65,7 +122,17 @@ fn main() {
     match vault::init_vault(db, semver::Version::parse(VERSION).unwrap()) {
         Ok(v) => {
             if is_server {
-                server::start(v);
+                let proxies = matches
+                    .subcommand_matches("server")
+                    .and_then(|m| m.value_of("trusted-proxies"))
+                    .or_else(|| config.get(Env::TrustedProxies))
+                    .unwrap_or_default()
+                    .parse::<TrustedProxies>()
+                    .unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        exit(-1);
+                    });
+                server::start(v, proxies);
             } else {
                 process_command(config, v, matches)
             }
@@ -75,9 +142,486 @@ fn main() {
 }
 
 fn process_command(config: Config, vault: Vault, matches: ArgMatches) {
+    match matches.subcommand() {
+        ("edit", Some(sub_m)) => {
+            let edit = match LinkEdit::from_matches(sub_m) {
+                Ok(edit) if !edit.is_empty() => edit,
+                Ok(_) => {
+                    eprintln!("Nothing to change.");
+                    exit(-1);
+                }
+                Err(flag) => {
+                    eprintln!("Unknown flag ({}).", flag);
+                    exit(-1);
+                }
+            };
+            match vault.edit_link(
+                &Authentication::from_matches(config, sub_m),
+                sub_m.value_of("url").unwrap_or_default(),
+                &edit,
+            ) {
+                Ok(Some(link)) => println!("Updated (id={})", link.id.unwrap()),
+                Ok(None) => {
+                    eprintln!("No such a link found");
+                    exit(-1);
+                }
+                Err(e) => {
+                    eprintln!("Error while updating a link ({:?})", e);
+                    exit(-1);
+                }
+            }
+        }
+        ("bulk", Some(sub_m)) => {
+            let action = if sub_m.is_present("delete") {
+                BulkAction::Delete
+            } else if sub_m.is_present("read") {
+                BulkAction::Read
+            } else if let Some(mut tags) = sub_m.values_of("rename-tag") {
+                let (from, to) = (tags.next().unwrap(), tags.next().unwrap());
+                BulkAction::RenameTag(from.to_string(), to.to_string())
+            } else {
+                match LinkEdit::from_matches(sub_m) {
+                    Ok(edit) if !edit.is_empty() => BulkAction::Edit(edit),
+                    Ok(_) => {
+                        eprintln!("Nothing to change.");
+                        exit(-1);
+                    }
+                    Err(flag) => {
+                        eprintln!("Unknown flag ({}).", flag);
+                        exit(-1);
+                    }
+                }
+            };
+            let dry_run = sub_m.is_present("dry-run");
+            match vault.bulk_links(
+                &Authentication::from_matches(config, sub_m),
+                sub_m.value_of("query").unwrap_or_default(),
+                &action,
+                dry_run,
+            ) {
+                Ok(result) => {
+                    let ids: Vec<_> = result.ids.iter().map(|id| id.to_string()).collect();
+                    if dry_run {
+                        println!("Would change {} links: {}", ids.len(), ids.join(","))
+                    } else {
+                        println!(
+                            "Changed {} links (version={}): {}",
+                            ids.len(),
+                            result.version,
+                            ids.join(",")
+                        )
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error while changing links ({:?}).", e);
+                    exit(-1);
+                }
+            }
+        }
+        ("purge", Some(sub_m)) => {
+            let days = sub_m
+                .value_of("days")
+                .and_then(|d| d.parse::<u32>().ok())
+                .expect("Number of days expected.");
+            match vault.purge_links(&Authentication::from_matches(config, sub_m), days) {
+                Ok(n) => println!("Purged {} links.", n),
+                Err(e) => {
+                    eprintln!("Error while purging links ({:?}).", e);
+                    exit(-1);
+                }
+            }
+        }
+        ("browse", Some(sub_m)) => {
+            if let Err(e) = browse::browse(
+                &vault,
+                &Authentication::from_matches(config, sub_m),
+                sub_m.value_of("query").unwrap_or_default(),
+            ) {
+                eprintln!("Error while browsing links ({}).", e);
+                exit(-1);
+            }
+        }
+        ("export", Some(sub_m)) => {
+            let format = sub_m
+                .value_of("format")
+                .and_then(|f| f.parse::<ExportFormat>().ok())
+                .expect("Unknown format.");
+            let with_queries = sub_m.is_present("with-queries");
+            if with_queries && format != ExportFormat::Json {
+                eprintln!("Stored queries can be exported only in json format.");
+                exit(-1);
+            }
+            match vault.export_links(
+                &Authentication::from_matches(config, sub_m),
+                sub_m.value_of("query").unwrap_or_default(),
+                format,
+                with_queries,
+            ) {
+                Ok(exported) => match sub_m.value_of("output") {
+                    Some(file) => fs::write(file, exported).expect("Cannot write file."),
+                    None => print!("{}", exported),
+                },
+                Err(e) => {
+                    eprintln!("Error while exporting links ({:?}).", e);
+                    exit(-1);
+                }
+            }
+        }
+        ("tags", Some(sub_m)) => match sub_m.subcommand() {
+            ("ls", Some(sub_m)) if sub_m.is_present("tree") => {
+                match vault.tags_tree(
+                    &Authentication::from_matches(config, sub_m),
+                    sub_m.value_of("pattern"),
+                ) {
+                    Ok(tree) => match output_format(sub_m) {
+                        OutputFormat::Json => println!("{}", miniserde::json::to_string(&tree)),
+                        OutputFormat::Plain => print_tags(&tree, 0),
+                        format => print_or_exit(&format, &flatten_tags(tree), |_| ()),
+                    },
+                    Err(e) => {
+                        eprintln!("Error while fetching tags ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("ls", Some(sub_m)) => {
+                match vault.tags_usage(
+                    &Authentication::from_matches(config, sub_m),
+                    sub_m.value_of("pattern"),
+                ) {
+                    Ok(tags) => print_or_exit(&output_format(sub_m), &tags, |tags| {
+                        for usage in tags {
+                            println!(
+                                "{} ({}) {}",
+                                usage.tag,
+                                usage.count,
+                                usage.used_at.as_str().dimmed()
+                            );
+                        }
+                    }),
+                    Err(e) => {
+                        eprintln!("Error while fetching tags ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("prune", Some(sub_m)) => {
+                match vault.prune_tags(&Authentication::from_matches(config, sub_m)) {
+                    Ok(n) => println!("Pruned {} tags.", n),
+                    Err(e) => {
+                        eprintln!("Error while pruning tags ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("related", Some(sub_m)) => {
+                let tags: Vec<_> = sub_m
+                    .values_of("tags")
+                    .map(|t| t.flat_map(|t| t.split(',')).map(String::from).collect())
+                    .unwrap_or_default();
+                let limit = sub_m
+                    .value_of("limit")
+                    .and_then(|l| l.parse::<u16>().ok())
+                    .expect("Number of tags expected.");
+                match vault.related_tags(
+                    &Authentication::from_matches(config, sub_m),
+                    &tags,
+                    sub_m.value_of("domain"),
+                    Some(limit),
+                ) {
+                    Ok(related) => print_or_exit(&output_format(sub_m), &related, |related| {
+                        for tag in related {
+                            println!("{} ({})", tag.tag, tag.score);
+                        }
+                    }),
+                    Err(e) => {
+                        eprintln!("Error while fetching tags ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("alias", Some(sub_m)) => match sub_m.subcommand() {
+                ("add", Some(sub_m)) => match vault.add_alias(
+                    &Authentication::from_matches(config, sub_m),
+                    sub_m.value_of("alias").unwrap(),
+                    sub_m.value_of("tag").unwrap(),
+                ) {
+                    Ok(alias) => println!("Added ({} -> {}).", alias.alias, alias.tag),
+                    Err(e) => {
+                        eprintln!("Error while adding alias ({}).", e);
+                        exit(-1);
+                    }
+                },
+                ("ls", Some(sub_m)) => match vault.find_aliases(
+                    &Authentication::from_matches(config, sub_m),
+                    sub_m.value_of("tag"),
+                ) {
+                    Ok(aliases) => print_or_exit(&output_format(sub_m), &aliases, |aliases| {
+                        for alias in aliases {
+                            println!("{} -> {}", alias.alias, alias.tag);
+                        }
+                    }),
+                    Err(e) => {
+                        eprintln!("Error while fetching aliases ({:?}).", e);
+                        exit(-1);
+                    }
+                },
+                ("rm", Some(sub_m)) => match vault.del_alias(
+                    &Authentication::from_matches(config, sub_m),
+                    sub_m.value_of("alias").unwrap(),
+                ) {
+                    Ok(Some(tag)) => {
+                        println!("Removed ({} -> {}).", sub_m.value_of("alias").unwrap(), tag)
+                    }
+                    Ok(None) => {
+                        eprintln!("No such an alias found");
+                        exit(-1);
+                    }
+                    Err(e) => {
+                        eprintln!("Error while removing alias ({:?}).", e);
+                        exit(-1);
+                    }
+                },
+                _ => (),
+            },
+            (cmd, Some(sub_m)) => {
+                let auth = Authentication::from_matches(config, sub_m);
+                let result = match cmd {
+                    "rename" => vault.rename_tag(
+                        &auth,
+                        sub_m.value_of("from").unwrap(),
+                        sub_m.value_of("to").unwrap(),
+                    ),
+                    "merge" => vault.merge_tags(
+                        &auth,
+                        &sub_m.values_of("from").unwrap().collect::<Vec<_>>(),
+                        sub_m.value_of("into").unwrap(),
+                    ),
+                    _ => vault
+                        .delete_tags(&auth, &sub_m.values_of("tag").unwrap().collect::<Vec<_>>()),
+                };
+                match result {
+                    Ok(result) => {
+                        let ids: Vec<_> = result.ids.iter().map(|id| id.to_string()).collect();
+                        println!(
+                            "Changed {} links (version={}): {}",
+                            ids.len(),
+                            result.version,
+                            ids.join(",")
+                        )
+                    }
+                    Err(e) => {
+                        eprintln!("Error while changing tags ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            _ => {}
+        },
+        ("users", Some(sub_m)) => match sub_m.subcommand() {
+            ("add", Some(sub_m)) => {
+                let pass = password_input(sub_m, "Initial password");
+                match vault.add_user(sub_m.value_of("login").unwrap(), &pass) {
+                    Ok(u) => println!("Added ({}).", u.login),
+                    Err(_) => {
+                        eprintln!("Error while adding new user. User might already exist.");
+                        exit(-1);
+                    }
+                }
+            }
+            ("import", Some(sub_m)) => {
+                let contents = read_file(sub_m.value_of("file").unwrap());
+                match vault.import_users(&contents) {
+                    Ok((added, skipped)) => {
+                        for login in &skipped {
+                            eprintln!("Skipped ({}), user already exists.", login);
+                        }
+                        println!("Added {} users, skipped {}.", added.len(), skipped.len());
+                    }
+                    Err(e) => {
+                        eprintln!("Error while importing users ({}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("del", Some(sub_m)) => {
+                match vault.del_user(sub_m.value_of("login").unwrap(), |u, c| {
+                    sub_m.is_present("yes")
+                        || confirm(&format!("User {} has {} links. Proceed?", u.login, c))
+                }) {
+                    Ok((u, is_deleted)) => {
+                        if is_deleted {
+                            println!("Removed ({}).", u.login)
+                        } else {
+                            println!("Abandoned.")
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error while removing user ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("passwd", Some(sub_m)) => {
+                let login = sub_m.value_of("login").unwrap();
+                if let Err(e) = vault.find_user(login) {
+                    eprintln!("Error while changing password ({:?}).", e);
+                    exit(-1);
+                }
+                match vault.passwd_user(login, &password_input(sub_m, "New password")) {
+                    Ok(u) => println!("Changed ({}).", u.login),
+                    Err(e) => {
+                        eprintln!("Error while changing password ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("ls", Some(sub_m)) => {
+                match vault.match_users(sub_m.value_of("login").unwrap_or_default()) {
+                    Ok(users) => print_or_exit(&output_format(sub_m), &users, |users| {
+                        for user in users {
+                            println!(
+                                "{} ({}){}{}",
+                                user.login,
+                                user.links,
+                                if user.admin { " [admin]" } else { "" },
+                                if user.disabled { " [disabled]" } else { "" }
+                            );
+                        }
+                    }),
+                    Err(_) => {
+                        eprintln!("Error while fetching users.");
+                        exit(-1);
+                    }
+                }
+            }
+            ("role", Some(sub_m)) => {
+                let login = sub_m.value_of("login").unwrap();
+                let admin = sub_m.value_of("role") == Some("admin");
+                match vault.set_admin(login, admin) {
+                    Ok(u) => println!("Changed ({}).", u.login),
+                    Err(e) => {
+                        eprintln!("Error while changing role ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("disable", Some(sub_m)) => {
+                match vault.set_disabled(sub_m.value_of("login").unwrap(), true) {
+                    Ok(u) => println!("Disabled ({}).", u.login),
+                    Err(e) => {
+                        eprintln!("Error while disabling user ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("enable", Some(sub_m)) => {
+                match vault.set_disabled(sub_m.value_of("login").unwrap(), false) {
+                    Ok(u) => println!("Enabled ({}).", u.login),
+                    Err(e) => {
+                        eprintln!("Error while enabling user ({:?}).", e);
+                        exit(-1);
+                    }
+                }
+            }
+            ("lockouts", Some(sub_m)) => match sub_m.subcommand() {
+                ("ls", Some(sub_m)) => match vault.find_lockouts(sub_m.value_of("subject")) {
+                    Ok(lockouts) => print_or_exit(&output_format(sub_m), &lockouts, |lockouts| {
+                        for l in lockouts {
+                            println!(
+                                "{} {} ({} failures, last: {}, locked until: {})",
+                                l.kind,
+                                l.subject,
+                                l.failures,
+                                l.failed_at,
+                                l.locked_until.as_deref().unwrap_or("-")
+                            );
+                        }
+                    }),
+                    Err(e) => {
+                        eprintln!("Error while fetching lockouts ({:?})", e);
+                        exit(-1);
+                    }
+                },
+                ("clear", Some(sub_m)) => match vault.clear_lockouts(sub_m.value_of("subject")) {
+                    Ok(n) => println!("Cleared ({}).", n),
+                    Err(e) => {
+                        eprintln!("Error while clearing lockouts ({:?})", e);
+                        exit(-1);
+                    }
+                },
+                _ => (),
+            },
+            ("token", Some(sub_m)) => match sub_m.subcommand() {
+                ("add", Some(sub_m)) => {
+                    let scope = sub_m
+                        .value_of("scope")
+                        .and_then(|s| s.parse::<Scope>().ok())
+                        .expect("Unknown scope.");
+                    match vault.add_token(
+                        sub_m.value_of("login").unwrap(),
+                        sub_m.value_of("name").unwrap(),
+                        scope,
+                        sub_m.value_of("expires"),
+                    ) {
+                        Ok((t, k)) => println!(
+                            "Generated API token ({}, scope={}): {}\n\
+                            It won't be shown again. Sample cURL:\n\n  \
+                            curl -H 'Authorization: Bearer {}' \
+                            \'http://localhost:8001/links?q=tags:rust\'\n",
+                            t.name, t.scope, k, k
+                        ),
+                        Err(e) => {
+                            eprintln!("Error while generating API token ({})", e);
+                            exit(-1);
+                        }
+                    }
+                }
+                ("ls", Some(sub_m)) => match vault.find_tokens(sub_m.value_of("login").unwrap()) {
+                    Ok(tokens) => print_or_exit(&output_format(sub_m), &tokens, |tokens| {
+                        for t in tokens {
+                            println!(
+                                "{} ({}) created: {}, last used: {}, expires: {}",
+                                t.name,
+                                t.scope,
+                                t.created_at,
+                                t.used_at.as_deref().unwrap_or("never"),
+                                t.expires_at.as_deref().unwrap_or("never")
+                            );
+                        }
+                    }),
+                    Err(e) => {
+                        eprintln!("Error while fetching API tokens ({:?})", e);
+                        exit(-1);
+                    }
+                },
+                ("revoke", Some(sub_m)) => match vault.revoke_token(
+                    sub_m.value_of("login").unwrap(),
+                    sub_m.value_of("name").unwrap(),
+                ) {
+                    Ok(Some(t)) => println!("Revoked ({}).", t.name),
+                    Ok(None) => {
+                        eprintln!("No such a token found");
+                        exit(-1);
+                    }
+                    Err(e) => {
+                        eprintln!("Error while revoking API token ({:?})", e);
+                        exit(-1);
+                    }
+                },
+                _ => (),
+            },
+            _ => (),
+        },
+        _ => process_links_command(config, &vault, &matches),
+    }
+}
+
+/// Processes commands which can be run against either local database or remote server.
+fn process_links_command(config: Config, backend: &dyn Backend, matches: &ArgMatches) {
     match matches.subcommand() {
         ("add", Some(sub_m)) => {
-            match vault.add_link(
+            match backend.add_link(
                 &Authentication::from_matches(config, sub_m),
                 Link::from_matches(sub_m),
             ) {
@@ -91,9 +635,9 @@ fn process_command(config: Config, vault: Vault, matches: ArgMatches) {
             }
         }
         ("del", Some(sub_m)) => {
-            match vault.del_link(
+            match backend.del_link(
                 &Authentication::from_matches(config, sub_m),
-                matches.value_of("url").unwrap_or("<unknown>"),
+                sub_m.value_of("url").unwrap_or("<unknown>"),
             ) {
                 Ok(Some(link)) => println!("Deleted (id={})", link.id.unwrap()),
                 Ok(None) => {
@@ -106,126 +650,258 @@ fn process_command(config: Config, vault: Vault, matches: ArgMatches) {
                 }
             }
         }
-        ("ls", Some(sub_m)) => {
+        ("ls", Some(sub_m)) if sub_m.is_present("facets") => {
             let auth = Authentication::from_matches(config, sub_m);
-            let query = sub_m.value_of("query").unwrap_or_default().to_string();
-            let links = if query.starts_with('@') {
-                let chunks: Vec<&str> = query.splitn(2, '/').collect();
-                let stored_query = chunks.first().unwrap().strip_prefix('@');
-                match vault.find_queries(&auth, stored_query, DBLookupType::Exact) {
-                    Ok(queries) => {
-                        if queries.len() != 1 {
-                            eprintln!("No stored query found ({})", stored_query.unwrap());
-                            exit(1);
-                        } else {
-                            let stored = queries.get(0).map(|q| q.query.clone()).unwrap();
-                            let query = chunks.get(1).unwrap_or(&"");
-                            vault.query_links(
-                                &auth,
-                                format!("{} {}", stored, query),
-                                Version::unknown(),
-                                None,
-                            )
+            let facets = backend
+                .expand_query(&auth, sub_m.value_of("query").unwrap_or_default())
+                .and_then(|q| backend.query_facets(&auth, &q, Version::unknown()));
+
+            match facets {
+                Ok(facets) => match output_format(sub_m) {
+                    OutputFormat::Json => println!("{}", miniserde::json::to_string(&facets)),
+                    OutputFormat::Plain => {
+                        for (name, counts) in [
+                            ("tags", facets.tags),
+                            ("flags", facets.flags),
+                            ("domains", facets.domains),
+                        ] {
+                            println!("{}:", name.bold());
+                            for c in counts {
+                                println!("  {} ({})", c.value, c.count);
+                            }
                         }
                     }
-                    Err(e) => {
-                        eprintln!("Error while fetching stored query ({:?}).", e);
-                        exit(-1);
-                    }
+                    format => print_or_exit(&format, &FacetRecord::from_facets(facets), |_| ()),
+                },
+                Err(e) => {
+                    eprintln!("Error while fetching facets ({:?}).", e);
+                    exit(-1);
                 }
-            } else {
-                vault.query_links(&auth, query, Version::unknown(), None)
-            };
+            }
+        }
+        ("ls", Some(sub_m)) => {
+            let auth = Authentication::from_matches(config, sub_m);
+            let format = output_format(sub_m);
+            let query = sub_m.value_of("query").unwrap_or_default();
+            let page = sub_m
+                .value_of("page")
+                .map(|p| p.parse::<usize>().ok().filter(|p| *p > 0))
+                .map(|p| p.expect("Page number expected."));
+            let limit = sub_m
+                .value_of("limit")
+                .map(|l| l.parse::<u16>().expect("Number of links expected."))
+                .unwrap_or(if page.is_some() { 20 } else { 0 });
+            let links = backend
+                .expand_query(&auth, query)
+                .and_then(|q| fetch_page(backend, &auth, &q, page.unwrap_or(1), limit));
 
             match links {
-                Ok((links, _)) => {
+                Ok(links) => print_or_exit(&format, &links, |links| {
                     let size = ts();
                     let tw = if let Some((Width(w), _)) = size {
                         w as i16
                     } else {
-                        i16::max_value()
+                        i16::MAX
                     };
                     for link in links {
                         let href_len = link.href.chars().count() as i16;
                         let desc_len = tw - href_len - 3;
                         println!("{} | {}", link.href, truncate(&link.name, desc_len).blue())
                     }
-                }
+                }),
                 Err(e) => {
                     eprintln!("Error while fetching links ({:?}).", e);
                     exit(-1);
                 }
             }
         }
-        ("import", Some(sub_m)) => {
-            let contents = read_file(sub_m.value_of("file").expect("Cannot read file."));
-            let links: Vec<Link> = json::from_str(&contents).expect("Invalid JSON.");
-            match vault.import_links(&Authentication::from_matches(config, sub_m), links) {
-                Ok(n) => println!("Imported {} links.", n),
-                Err(e) => {
-                    eprintln!("Error while importing links ({:?}).", e);
-                    exit(-1);
-                }
-            }
-        }
-        ("users", Some(sub_m)) => match sub_m.subcommand() {
+        ("queries", Some(sub_m)) => match sub_m.subcommand() {
             ("add", Some(sub_m)) => {
-                let pass = password(None, Some("Initial password"));
-                match vault.add_user(sub_m.value_of("login").unwrap(), &pass) {
-                    Ok(u) => println!("Added ({}).", u.login),
-                    Err(_) => {
-                        eprintln!("Error while adding new user. User might already exist.");
+                let name = sub_m.value_of("name").unwrap();
+                match backend.store_query(
+                    &Authentication::from_matches(config, sub_m),
+                    name,
+                    sub_m.value_of("query").unwrap(),
+                ) {
+                    Ok(_) => println!("Stored (@{}).", name),
+                    Err(e) => {
+                        eprintln!("Error while storing query ({}).", e);
                         exit(-1);
                     }
                 }
             }
-            ("del", Some(sub_m)) => match vault.del_user(sub_m.value_of("login").unwrap()) {
-                Ok((u, is_deleted)) => {
-                    if is_deleted {
-                        println!("Removed ({}).", u.login)
-                    } else {
-                        println!("Abandoned.")
+            ("ls", Some(sub_m)) => match backend.find_queries(
+                &Authentication::from_matches(config, sub_m),
+                Some(sub_m.value_of("pattern").unwrap_or_default()),
+                DBLookupType::Patterned,
+            ) {
+                Ok(queries) => print_or_exit(&output_format(sub_m), &queries, |queries| {
+                    for query in queries {
+                        println!("{} | {}", query.name, query.query.blue());
                     }
+                }),
+                Err(e) => {
+                    eprintln!("Error while fetching queries ({:?}).", e);
+                    exit(-1);
+                }
+            },
+            ("show", Some(sub_m)) => match backend.find_queries(
+                &Authentication::from_matches(config, sub_m),
+                sub_m.value_of("name"),
+                DBLookupType::Exact,
+            ) {
+                Ok(queries) if !queries.is_empty() => println!("{}", queries[0].query),
+                Ok(_) => {
+                    eprintln!("No such a query found");
+                    exit(-1);
                 }
                 Err(e) => {
-                    eprintln!("Error while removing user ({:?}).", e);
+                    eprintln!("Error while fetching query ({:?}).", e);
                     exit(-1);
                 }
             },
-            ("passwd", Some(sub_m)) => match vault.passwd_user(sub_m.value_of("login").unwrap()) {
-                Ok(u) => println!("Changed ({}).", u.login),
+            ("rename", Some(sub_m)) => match backend.rename_query(
+                &Authentication::from_matches(config, sub_m),
+                sub_m.value_of("from").unwrap(),
+                sub_m.value_of("to").unwrap(),
+            ) {
+                Ok(Some(query)) => println!(
+                    "Renamed (@{} -> @{}).",
+                    sub_m.value_of("from").unwrap(),
+                    query.name
+                ),
+                Ok(None) => {
+                    eprintln!("No such a query found");
+                    exit(-1);
+                }
                 Err(e) => {
-                    eprintln!("Error while changing password ({:?}).", e);
+                    eprintln!("Error while renaming query ({}).", e);
                     exit(-1);
                 }
             },
-            ("ls", Some(sub_m)) => {
-                match vault.match_users(sub_m.value_of("login").unwrap_or_default()) {
-                    Ok(users) => {
-                        for (user, count) in users {
-                            println!("{} ({})", user, count);
-                        }
-                    }
-                    Err(_) => {
-                        eprintln!("Error while fetching users.");
-                        exit(-1);
-                    }
+            ("rm", Some(sub_m)) => match backend.del_query(
+                &Authentication::from_matches(config, sub_m),
+                sub_m.value_of("name").unwrap(),
+            ) {
+                Ok(Some(query)) => println!("Removed (@{}).", query.name),
+                Ok(None) => {
+                    eprintln!("No such a query found");
+                    exit(-1);
                 }
-            }
-            ("token", Some(sub_m)) => match vault.generate_key(sub_m.value_of("login").unwrap()) {
-                Ok((_u, k)) => println!(
-                    "Generated API key: {}\nSample cURL:\n\n  \
-                    curl -H 'Authorization: Bearer {}' \
-                    \'http://localhost:8001/links?q=tags:rust\'\n",
-                    k, k
-                ),
                 Err(e) => {
-                    eprintln!("Error while generating API key ({:?})", e);
+                    eprintln!("Error while removing query ({:?}).", e);
                     exit(-1);
                 }
             },
             _ => (),
         },
+        ("import", Some(sub_m)) => {
+            let contents = read_file(sub_m.value_of("file").expect("Cannot read file."));
+            let format = sub_m
+                .value_of("format")
+                .and_then(|f| f.parse::<ImportFormat>().ok());
+            let policy = sub_m
+                .value_of("on-conflict")
+                .and_then(|p| p.parse::<ConflictPolicy>().ok())
+                .expect("Unknown conflict policy.");
+            let dry_run = sub_m.is_present("dry-run");
+            match backend.import_file(
+                &Authentication::from_matches(config, sub_m),
+                &contents,
+                format,
+                policy,
+                dry_run,
+            ) {
+                Ok(summary) => {
+                    if dry_run {
+                        for link in &summary.links {
+                            println!("{:>9} {}", link.status, link.href);
+                        }
+                    }
+                    for error in &summary.errors {
+                        eprintln!("Skipped {}", error);
+                    }
+                    if dry_run {
+                        println!("Would import links: {}.", summary)
+                    } else {
+                        println!("Imported links: {}.", summary)
+                    }
+                }
+                Err(BadImport(e)) => {
+                    eprintln!("Error while reading links ({}).", e);
+                    exit(-1);
+                }
+                Err(e) => {
+                    eprintln!("Error while importing links ({:?}).", e);
+                    exit(-1);
+                }
+            }
+        }
         _ => {}
     }
 }
+
+/// Reads password from a file or standard input, if asked to, or prompts for it otherwise.
+fn password_input(matches: &ArgMatches, prompt: &str) -> String {
+    let input = if let Some(file) = matches.value_of("password-file") {
+        read_file(file)
+    } else if matches.is_present("password-stdin") {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_line(&mut input)
+            .expect("Password expected on standard input.");
+        input
+    } else {
+        return password(None, Some(prompt));
+    };
+    let pass = input.trim_end_matches(&['\r', '\n'][..]);
+    if pass.is_empty() {
+        eprintln!("Password can't be empty.");
+        exit(-1);
+    }
+    pass.to_string()
+}
+
+fn output_format(matches: &ArgMatches) -> OutputFormat {
+    OutputFormat::from_matches(matches).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(-1);
+    })
+}
+
+fn print_or_exit<R: Record, F: Fn(&[R])>(format: &OutputFormat, records: &[R], plain: F) {
+    if let Err(e) = print_records(format, records, plain) {
+        eprintln!("{}", e);
+        exit(-1);
+    }
+}
+
+fn print_tags(nodes: &[TagNode], depth: usize) {
+    for node in nodes {
+        println!("{}{} ({})", "  ".repeat(depth), node.name, node.count);
+        print_tags(&node.children, depth + 1);
+    }
+}
+
+/// Fetches n-th page of links, following cursors of all the preceding pages.
+fn fetch_page(
+    backend: &dyn Backend,
+    auth: &Option<Authentication>,
+    query: &str,
+    page: usize,
+    limit: u16,
+) -> DBResult<Vec<Link>> {
+    let mut cursor = None;
+    for n in 1..=page {
+        let (links, _, next) =
+            backend.query_page(auth, query, Version::unknown(), limit, cursor)?;
+        if n == page {
+            return Ok(links);
+        }
+        if next.is_none() {
+            break;
+        }
+        cursor = next;
+    }
+    Ok(Vec::new())
+}
@@ -0,0 +1,373 @@
+use crate::utils::escape_csv;
+use crate::vault::facets::Facets;
+use crate::vault::link::Link;
+use crate::vault::lockout::Lockout;
+use crate::vault::stored_query::StoredQuery;
+use crate::vault::tags::{RelatedTag, TagAlias, TagNode, TagUsage};
+use crate::vault::token::Token;
+use crate::vault::user::Account;
+
+use clap::ArgMatches;
+use miniserde::{json, Serialize};
+
+/// Format of listed records.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Human-readable output, specific to each command
+    Plain,
+    Json,
+    /// One json object per line
+    Jsonl,
+    Tsv,
+    Csv,
+    /// User-supplied pattern with `{field}` placeholders
+    Template(String),
+}
+
+impl OutputFormat {
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let template = matches.value_of("template");
+        match (matches.value_of("format"), template) {
+            (None, None) => Ok(OutputFormat::Plain),
+            (None, Some(t)) | (Some("template"), Some(t)) => {
+                Ok(OutputFormat::Template(unescape(t)))
+            }
+            (Some("template"), None) => Err("Template expected (--template).".into()),
+            (Some(_), Some(_)) => Err("Template can be used with template format only.".into()),
+            (Some("json"), None) => Ok(OutputFormat::Json),
+            (Some("jsonl"), None) => Ok(OutputFormat::Jsonl),
+            (Some("tsv"), None) => Ok(OutputFormat::Tsv),
+            (Some("csv"), None) => Ok(OutputFormat::Csv),
+            (Some(f), None) => Err(format!("Unknown format ({}).", f)),
+        }
+    }
+}
+
+/// Record which can be printed in any of output formats.
+pub trait Record: Serialize {
+    /// Names of fields, in the order of columns.
+    const FIELDS: &'static [&'static str];
+
+    /// Values of fields, in the order of [Record::FIELDS].
+    fn values(&self) -> Vec<String>;
+}
+
+/// Prints records in requested format. Plain format is up to given printer.
+pub fn print_records<R: Record, F: Fn(&[R])>(
+    format: &OutputFormat,
+    records: &[R],
+    plain: F,
+) -> Result<(), String> {
+    match format {
+        OutputFormat::Plain => plain(records),
+        OutputFormat::Json => println!("{}", json::to_string(&records)),
+        OutputFormat::Jsonl => records
+            .iter()
+            .for_each(|r| println!("{}", json::to_string(r))),
+        OutputFormat::Tsv => {
+            println!("{}", R::FIELDS.join("\t"));
+            for record in records {
+                let values: Vec<_> = record
+                    .values()
+                    .iter()
+                    .map(|v| v.replace(['\t', '\n', '\r'], " "))
+                    .collect();
+                println!("{}", values.join("\t"));
+            }
+        }
+        OutputFormat::Csv => {
+            println!("{}", R::FIELDS.join(","));
+            for record in records {
+                let values: Vec<_> = record.values().iter().map(|v| escape_csv(v)).collect();
+                println!("{}", values.join(","));
+            }
+        }
+        OutputFormat::Template(template) => {
+            let template = Template::parse(template, R::FIELDS)?;
+            for record in records {
+                println!("{}", template.render(&record.values()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parsed template, with placeholders resolved to indexes of fields.
+#[derive(Debug, PartialEq)]
+struct Template(Vec<Chunk>);
+
+#[derive(Debug, PartialEq)]
+enum Chunk {
+    Text(String),
+    Field(usize),
+}
+
+impl Template {
+    fn parse(template: &str, fields: &[&str]) -> Result<Self, String> {
+        let mut chunks = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+            let name = &rest[start + 1..end];
+            if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                chunks.push(Chunk::Text(rest[..=start].to_string()));
+                rest = &rest[start + 1..];
+                continue;
+            }
+            let field = fields.iter().position(|f| *f == name).ok_or_else(|| {
+                format!(
+                    "Unknown placeholder {{{}}}, expected one of: {}.",
+                    name,
+                    fields.join(", ")
+                )
+            })?;
+            chunks.push(Chunk::Text(rest[..start].to_string()));
+            chunks.push(Chunk::Field(field));
+            rest = &rest[end + 1..];
+        }
+        chunks.push(Chunk::Text(rest.to_string()));
+        Ok(Template(chunks))
+    }
+    fn render(&self, values: &[String]) -> String {
+        self.0
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Text(text) => text.as_str(),
+                Chunk::Field(i) => values[*i].as_str(),
+            })
+            .collect()
+    }
+}
+
+/// Turns escaped tabs and new lines, which are hard to type in a shell, into real ones.
+fn unescape(template: &str) -> String {
+    template
+        .replace("\\t", "\t")
+        .replace("\\n", "\n")
+        .replace("\\\\", "\\")
+}
+
+fn flag(value: bool) -> String {
+    if value { "1" } else { "0" }.to_string()
+}
+
+impl Record for Link {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "href",
+        "name",
+        "description",
+        "tags",
+        "toread",
+        "shared",
+        "favourite",
+        "created_at",
+        "updated_at",
+        "read_at",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.id.map(|id| id.to_string()).unwrap_or_default(),
+            self.href.clone(),
+            self.name.clone(),
+            self.description.clone().unwrap_or_default(),
+            self.tags.as_ref().map(|t| t.join(",")).unwrap_or_default(),
+            flag(self.toread),
+            flag(self.shared),
+            flag(self.favourite),
+            self.created_at.clone(),
+            self.updated_at.clone().unwrap_or_default(),
+            self.read_at.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl Record for TagUsage {
+    const FIELDS: &'static [&'static str] = &["tag", "count", "used_at"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.tag.clone(),
+            self.count.to_string(),
+            self.used_at.clone(),
+        ]
+    }
+}
+
+impl Record for TagNode {
+    const FIELDS: &'static [&'static str] = &["tag", "name", "count"];
+
+    fn values(&self) -> Vec<String> {
+        vec![self.tag.clone(), self.name.clone(), self.count.to_string()]
+    }
+}
+
+/// Lists all the nodes of a tree one by one, parents first.
+pub fn flatten_tags(nodes: Vec<TagNode>) -> Vec<TagNode> {
+    nodes
+        .into_iter()
+        .flat_map(|mut node| {
+            let children = std::mem::take(&mut node.children);
+            std::iter::once(node).chain(flatten_tags(children))
+        })
+        .collect()
+}
+
+impl Record for RelatedTag {
+    const FIELDS: &'static [&'static str] = &["tag", "score"];
+
+    fn values(&self) -> Vec<String> {
+        vec![self.tag.clone(), self.score.to_string()]
+    }
+}
+
+impl Record for TagAlias {
+    const FIELDS: &'static [&'static str] = &["alias", "tag"];
+
+    fn values(&self) -> Vec<String> {
+        vec![self.alias.clone(), self.tag.clone()]
+    }
+}
+
+impl Record for StoredQuery {
+    const FIELDS: &'static [&'static str] = &["id", "name", "query"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.id.map(|id| id.to_string()).unwrap_or_default(),
+            self.name.clone(),
+            self.query.clone(),
+        ]
+    }
+}
+
+impl Record for Account {
+    const FIELDS: &'static [&'static str] = &["login", "links", "admin", "disabled"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.login.clone(),
+            self.links.to_string(),
+            self.admin.to_string(),
+            self.disabled.to_string(),
+        ]
+    }
+}
+
+impl Record for Token {
+    const FIELDS: &'static [&'static str] =
+        &["name", "scope", "created_at", "used_at", "expires_at"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.scope.clone(),
+            self.created_at.clone(),
+            self.used_at.clone().unwrap_or_default(),
+            self.expires_at.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl Record for Lockout {
+    const FIELDS: &'static [&'static str] =
+        &["kind", "subject", "failures", "failed_at", "locked_until"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.kind.clone(),
+            self.subject.clone(),
+            self.failures.to_string(),
+            self.failed_at.clone(),
+            self.locked_until.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+/// Single facet count, like number of links tagged with `rust`.
+#[derive(Serialize, Clone, Debug)]
+pub struct FacetRecord {
+    pub facet: String,
+    pub value: String,
+    pub count: u32,
+}
+
+impl FacetRecord {
+    pub fn from_facets(facets: Facets) -> Vec<FacetRecord> {
+        let facets = vec![
+            ("tags", facets.tags),
+            ("flags", facets.flags),
+            ("domains", facets.domains),
+        ];
+        facets
+            .into_iter()
+            .flat_map(|(facet, counts)| {
+                counts.into_iter().map(move |c| FacetRecord {
+                    facet: facet.to_string(),
+                    value: c.value,
+                    count: c.count,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Record for FacetRecord {
+    const FIELDS: &'static [&'static str] = &["facet", "value", "count"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.facet.clone(),
+            self.value.clone(),
+            self.count.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test_output {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn test_template() {
+        let template = Template::parse("{href}\t{name} {x-y} {", &["href", "name"]).unwrap();
+        assert_eq!(
+            "https://foo.io\tFoo {x-y} {",
+            template.render(&["https://foo.io".into(), "Foo".into()])
+        );
+        assert!(Template::parse("{href} {tagz}", &["href", "tags"])
+            .unwrap_err()
+            .contains("{tagz}"));
+    }
+
+    #[rstest]
+    #[case("{href}\\t{name}\\n", "{href}\t{name}\n")]
+    #[case("a\\\\b", "a\\b")]
+    fn test_unescape(#[case] template: &str, #[case] expected: &str) {
+        assert_eq!(expected, unescape(template));
+    }
+
+    #[rstest]
+    fn test_link_values() {
+        let link = Link::new(
+            Some(1),
+            "https://foo.io",
+            "Foo",
+            None,
+            Some(vec!["a".into(), "b".into()]),
+        )
+        .set_toread(true);
+        let values = link.values();
+
+        assert_eq!(Link::FIELDS.len(), values.len());
+        assert_eq!(
+            vec!["1", "https://foo.io", "Foo", "", "a,b", "1", "0", "0"],
+            values[..8].to_vec()
+        );
+    }
+}
@@ -1,15 +1,26 @@
-use crate::db::DBLookupType;
+use crate::db::DBError::{BadCursor, BadImport, BadQuery, UnknownUser};
+use crate::db::{DBLookupType, DBResult};
 use crate::server::json::*;
+use crate::server::proxy::TrustedProxies;
 use crate::server::request::*;
 use crate::server::response::*;
 use crate::vault::auth::Authentication;
-use crate::vault::link::{Link, Version};
+use crate::vault::bulk::BulkAction;
+use crate::vault::export::ExportFormat;
+use crate::vault::facets::Facets;
+use crate::vault::import::{ConflictPolicy, ImportFormat};
+use crate::vault::link::{Link, LinkEdit, Version};
+use crate::vault::search::cursor::Cursor;
+use crate::vault::search::{self, Search};
+use crate::vault::token::Scope;
+use crate::vault::user::Account;
 use crate::vault::Vault;
 
 use failure::Error;
 use log::error;
 use rouille::{content_encoding, router, try_or_400, Request, Response};
 use std::collections::HashMap;
+use std::io::Read;
 
 pub type HandlerResult = Result<Response, Error>;
 
@@ -25,11 +36,154 @@ fn lookup_type(request: &Request) -> DBLookupType {
         })
 }
 
-pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
+/// Decodes cursor of requested page. Empty cursor stands for the first page.
+fn cursor(request: &Request) -> DBResult<Option<Cursor>> {
+    match request.get_param("cursor").filter(|c| !c.is_empty()) {
+        Some(c) => Cursor::decode(&c).map(Some).ok_or(BadCursor),
+        None => Ok(None),
+    }
+}
+
+fn links_response(
+    request: &Request,
+    links: Vec<Link>,
+    version: Version,
+    next: Option<Cursor>,
+    facets: Option<Facets>,
+) -> Response {
+    let (version, next_cursor) = (version.offset(), next.map(|c| c.encode()));
+    let response = match facets {
+        Some(facets) => json_output(FacetedLinksResponse {
+            version,
+            links,
+            next_cursor,
+            facets,
+        }),
+        None => json_output(LinksResponse {
+            version,
+            links,
+            next_cursor,
+        }),
+    };
+    content_encoding::apply(request, response)
+}
+
+/// Checks if facets were requested along with links.
+fn with_facets(request: &Request) -> bool {
+    request
+        .get_param("facets")
+        .is_some_and(|f| f.to_lowercase() == "true")
+}
+
+/// Splits comma-separated list of values.
+fn split(values: Option<String>) -> Vec<String> {
+    values
+        .unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// Scope of API token required by given request. Reading requires read-only scope,
+/// any change requires write scope, administration requires admin one.
+fn required_scope(request: &Request) -> Scope {
+    if request.url().starts_with("/admin") {
+        Scope::Admin
+    } else if request.method() == "GET" || request.method() == "HEAD" {
+        Scope::Read
+    } else {
+        Scope::Write
+    }
+}
+
+/// Handles users administration. Available only to users with admin role.
+fn admin_handler(request: &Request, vault: &Vault, auth: &Option<Authentication>) -> Response {
+    let admin = match vault.authorize_admin(auth) {
+        Ok(admin) => admin,
+        Err(e) => return err_response(e),
+    };
+
+    #[allow(clippy::manual_strip)]
+    let result = router!(request,
+        (GET) (/admin/users) => {
+            vault
+                .match_users(&request.get_param("q").unwrap_or_default())
+                .map(|users| content_encoding::apply(request, json_output(users)))
+        },
+        (POST) (/admin/users) => {
+            match json_input::<NewUserRequest>(request) {
+                Ok(u) => vault.add_user(&u.login, &u.password).and_then(|user| {
+                    let admin = u.admin.unwrap_or(false);
+                    vault.set_admin(&user.login, admin)?;
+                    Ok(json_output(Account {
+                        login: user.login,
+                        links: 0,
+                        admin,
+                        disabled: false,
+                    })
+                    .with_status_code(201))
+                }),
+                Err(e) => {
+                    let json = try_or_400::ErrJson::from_err(&e);
+                    Ok(Response::json(&json).with_status_code(400))
+                }
+            }
+        },
+        (POST) (/admin/users/{login: String}/password) => {
+            match json_input::<PasswordRequest>(request) {
+                Ok(p) => vault
+                    .passwd_user(&login, &p.password)
+                    .map(|_| Response::empty_204()),
+                Err(e) => {
+                    let json = try_or_400::ErrJson::from_err(&e);
+                    Ok(Response::json(&json).with_status_code(400))
+                }
+            }
+        },
+        (POST) (/admin/users/{login: String}/disable) => {
+            vault.find_user(&login).and_then(|(user, _)| {
+                if user.id == admin.id {
+                    Ok(Response::text("Can't disable own account").with_status_code(400))
+                } else {
+                    vault.set_disabled(&user.login, true).map(|_| Response::empty_204())
+                }
+            })
+        },
+        (POST) (/admin/users/{login: String}/enable) => {
+            vault.set_disabled(&login, false).map(|_| Response::empty_204())
+        },
+        (DELETE) (/admin/users/{login: String}) => {
+            vault.find_user(&login).and_then(|(user, _)| {
+                if user.id == admin.id {
+                    Ok(Response::text("Can't remove own account").with_status_code(400))
+                } else {
+                    vault.del_user(&user.login, |_, _| true).map(|_| Response::empty_204())
+                }
+            })
+        },
+        _ => Ok(Response::empty_404())
+    );
+    match result {
+        Ok(response) => response,
+        Err(UnknownUser) => Response::empty_404(),
+        Err(e) => err_response(e),
+    }
+}
+
+pub fn api_handler(request: &Request, vault: &Vault, proxies: &TrustedProxies) -> HandlerResult {
     let token = request
         .header("authorization")
         .and_then(|header| header.split_whitespace().last());
     let auth = Authentication::from_token(token);
+    if auth.is_some() {
+        if let Err(e) = vault.authorize(&auth, required_scope(request)) {
+            return Ok(err_response(e));
+        }
+    }
+    if request.url().starts_with("/admin/") {
+        return Ok(admin_handler(request, vault, &auth));
+    }
     let limit = request
         .get_param("limit")
         .and_then(|v| v.parse::<u16>().ok());
@@ -42,15 +196,16 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
             .unwrap_or(-1),
     );
 
-    #[allow(clippy::clippy::manual_strip)]
+    #[allow(clippy::manual_strip)]
     let resp = router!(request,
         (GET) (/version) => {
             Response::text(env!("CARGO_PKG_VERSION"))
         },
         (POST) (/auth) => {
+            let client = proxies.client_addr(request).to_string();
             match json_input::<AuthRequest>(request) {
-                Ok(t) => match vault.user_info(&Authentication::from_credentials(t.login, t.password)) {
-                    Ok(user_info) => content_encoding::apply(request, json_output(user_info)),
+                Ok(t) => match vault.login(&Authentication::from_credentials(t.login, t.password), Some(&client)) {
+                    Ok(session) => content_encoding::apply(request, json_output(session)),
                     Err(e) => err_response(e)
                 }
                 Err(e) => {
@@ -59,12 +214,51 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
                 }
             }
         },
+        (POST) (/auth/refresh) => {
+            match json_input::<RefreshRequest>(request) {
+                Ok(t) => match vault.refresh_session(&t.refresh_token) {
+                    Ok(session) => json_output(session),
+                    Err(e) => err_response(e)
+                }
+                Err(e) => {
+                    let json = try_or_400::ErrJson::from_err(&e);
+                    Response::json(&json).with_status_code(400)
+                }
+            }
+        },
+        (POST) (/auth/logout) => {
+            match vault.logout(&auth) {
+                Ok(_) => Response::empty_204(),
+                Err(e) => err_response(e)
+            }
+        },
         (GET) (/tags) => {
             let pattern = request.get_param("name");
-            let exclude = request.get_param("exclude")
-                .map(|e| e.split(',').map(|v| v.trim().to_string()).collect());
+            if request.get_param("tree").is_some_and(|t| t == "true") {
+                match vault.tags_tree(&auth, pattern.as_deref()) {
+                    Ok(tree) => {
+                        let mut result = HashMap::new();
+                        result.insert("tags", tree);
+                        content_encoding::apply(request, json_output(result))
+                    }
+                    Err(e) => err_response(e)
+                }
+            } else {
+                let exclude = request.get_param("exclude")
+                    .map(|e| e.split(',').map(|v| v.trim().to_string()).collect());
 
-            match vault.recent_tags(&auth, pattern.as_deref(), exclude, limit) {
+                match vault.recent_tags(&auth, pattern.as_deref(), exclude, limit) {
+                    Ok(tags) => {
+                        let mut result = HashMap::new();
+                        result.insert("tags", tags);
+                        content_encoding::apply(request, json_output(result))
+                    }
+                    Err(e) => err_response(e)
+                }
+            }
+        },
+        (GET) (/tags/usage) => {
+            match vault.tags_usage(&auth, request.get_param("name").as_deref()) {
                 Ok(tags) => {
                     let mut result = HashMap::new();
                     result.insert("tags", tags);
@@ -73,6 +267,96 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
                 Err(e) => err_response(e)
             }
         },
+        (GET) (/tags/related) => {
+            let tags = split(request.get_param("tags"));
+            let domain = request.get_param("domain").or_else(|| request.get_param("href"));
+            match vault.related_tags(&auth, &tags, domain.as_deref(), limit) {
+                Ok(tags) => {
+                    let mut result = HashMap::new();
+                    result.insert("tags", tags);
+                    content_encoding::apply(request, json_output(result))
+                }
+                Err(e) => err_response(e)
+            }
+        },
+        (GET) (/tags/aliases) => {
+            match vault.find_aliases(&auth, request.get_param("tag").as_deref()) {
+                Ok(aliases) => {
+                    let mut result = HashMap::new();
+                    result.insert("aliases", aliases);
+                    content_encoding::apply(request, json_output(result))
+                }
+                Err(e) => err_response(e)
+            }
+        },
+        (POST) (/tags/aliases) => {
+            match json_input::<TagAliasRequest>(request) {
+                Ok(t) => match vault.add_alias(&auth, &t.alias, &t.tag) {
+                    Ok(alias) => json_output(alias),
+                    Err(e) => err_response(e)
+                },
+                Err(e) => {
+                    let json = try_or_400::ErrJson::from_err(&e);
+                    Response::json(&json).with_status_code(400)
+                }
+            }
+        },
+        (DELETE) (/tags/aliases) => {
+            match vault.del_alias(&auth, &request.get_param("alias").unwrap_or_default()) {
+                Ok(Some(_)) => Response::empty_204(),
+                Ok(None) => Response::empty_404(),
+                Err(e) => err_response(e)
+            }
+        },
+        (POST) (/tags/rename) => {
+            match json_input::<TagRenameRequest>(request) {
+                Ok(t) => match vault.rename_tag(&auth, &t.from, &t.to) {
+                    Ok(result) => json_output(result),
+                    Err(e) => err_response(e)
+                },
+                Err(e) => {
+                    let json = try_or_400::ErrJson::from_err(&e);
+                    Response::json(&json).with_status_code(400)
+                }
+            }
+        },
+        (POST) (/tags/merge) => {
+            match json_input::<TagMergeRequest>(request) {
+                Ok(t) => {
+                    let from: Vec<_> = t.from.iter().map(String::as_str).collect();
+                    match vault.merge_tags(&auth, &from, &t.into) {
+                        Ok(result) => json_output(result),
+                        Err(e) => err_response(e)
+                    }
+                }
+                Err(e) => {
+                    let json = try_or_400::ErrJson::from_err(&e);
+                    Response::json(&json).with_status_code(400)
+                }
+            }
+        },
+        (POST) (/tags/prune) => {
+            match vault.prune_tags(&auth) {
+                Ok(n) => {
+                    let mut result = HashMap::new();
+                    result.insert("pruned", n);
+                    json_output(result)
+                }
+                Err(e) => err_response(e)
+            }
+        },
+        (DELETE) (/tags) => {
+            let tags = split(request.get_param("name"));
+            if tags.is_empty() {
+                Response::text("Tags to remove are required").with_status_code(400)
+            } else {
+                let tags: Vec<_> = tags.iter().map(String::as_str).collect();
+                match vault.delete_tags(&auth, &tags) {
+                    Ok(result) => json_output(result),
+                    Err(e) => err_response(e)
+                }
+            }
+        },
         (POST) (/queries) => {
             match json_input::<QueryRequest>(request) {
                 Ok(t) => {
@@ -87,6 +371,19 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
                 }
             }
         },
+        (POST) (/queries/rename) => {
+            match json_input::<QueryRenameRequest>(request) {
+                Ok(t) => match vault.rename_query(&auth, &t.from, &t.to) {
+                    Ok(Some(query)) => json_output(query),
+                    Ok(None) => Response::empty_404(),
+                    Err(e) => err_response(e)
+                },
+                Err(e) => {
+                    let json = try_or_400::ErrJson::from_err(&e);
+                    Response::json(&json).with_status_code(400)
+                }
+            }
+        },
         (DELETE) (/queries/{id: i64}) => {
             let result = vault.del_query(&auth, id);
             match result {
@@ -103,15 +400,22 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
         },
         (GET) (/links) => {
             let query = request.get_param("q").unwrap_or_default();
-            let result = match lookup_type(request) {
-                DBLookupType::Patterned => vault.query_links(&auth, query, version.clone(), limit),
-                DBLookupType::Exact => {
-                    let pattern = Link::new(None, query.as_str(), "", None, None);
-                    vault.find_links(&auth, pattern, DBLookupType::Exact, version.clone(), limit)
-                }
+            let search = match lookup_type(request) {
+                DBLookupType::Patterned => search::parse(&query).map_err(BadQuery),
+                DBLookupType::Exact => Ok(Search::url(&query))
             };
+            let result = search.and_then(|s| {
+                let facets = if with_facets(request) {
+                    Some(vault.find_facets(&auth, &s, version.clone())?)
+                } else {
+                    None
+                };
+                let cursor = cursor(request)?;
+                let (links, version, next) = vault.find_page(&auth, &s, version.clone(), limit.unwrap_or(0), cursor)?;
+                Ok((links, version, next, facets))
+            });
             match result {
-                Ok((links, version)) => content_encoding::apply(request, json_output(LinksResponse{links, version: version.offset()})),
+                Ok((links, version, next, facets)) => links_response(request, links, version, next, facets),
                 Err(e) => err_response(e)
             }
         },
@@ -136,9 +440,14 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
                             .set_toread(flags.contains("toread"))
                             .set_shared(flags.contains("shared"))
                             .set_favourite(flags.contains("favourite"))
+                            .set_timestamp(link.created_at.unwrap_or_default())
+                            .set_updated_at(link.updated_at)
+                            .set_read_at(link.read_at)
                     }).collect();
-                    vault.add_links(&auth, links, version)?;
-                    Response::empty_204()
+                    match vault.add_links(&auth, links, version) {
+                        Ok(_) => Response::empty_204(),
+                        Err(e) => err_response(e)
+                    }
                 }
                 Err(e) => {
                     error!("{:?}", e);
@@ -146,6 +455,37 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
                 }
             }
         },
+        (POST) (/links/bulk) => {
+            match json_input::<BulkRequest>(request) {
+                Ok(bulk) => {
+                    let action = match bulk.action.as_str() {
+                        "edit" => LinkEdit::default()
+                            .set_tags(split(bulk.tags))
+                            .set_flags(&split(bulk.flags))
+                            .map(BulkAction::Edit)
+                            .map_err(|flag| format!("Unknown flag: {}", flag)),
+                        "rename-tag" => match (bulk.from, bulk.to) {
+                            (Some(from), Some(to)) => Ok(BulkAction::RenameTag(from, to)),
+                            _ => Err("Tags to rename from and to are required".to_string())
+                        },
+                        "read" => Ok(BulkAction::Read),
+                        "delete" => Ok(BulkAction::Delete),
+                        action => Err(format!("Unknown action: {}", action))
+                    };
+                    match action {
+                        Ok(action) => match vault.bulk_links(&auth, &bulk.query, &action, bulk.dry_run.unwrap_or_default()) {
+                            Ok(result) => json_output(result),
+                            Err(e) => err_response(e)
+                        },
+                        Err(e) => Response::text(e).with_status_code(400)
+                    }
+                }
+                Err(e) => {
+                    let json = try_or_400::ErrJson::from_err(&e);
+                    Response::json(&json).with_status_code(400)
+                }
+            }
+        },
         (DELETE) (/links/{id: i64}) => {
             match vault.get_href(&auth, id) {
                 Ok(href) => {
@@ -158,6 +498,30 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
                 _ => Response::empty_404()
             }
         },
+        (PATCH) (/links/{id: i64}) => {
+            match json_input::<LinkPatchData>(request) {
+                Ok(patch) => {
+                    let edit = LinkEdit::default()
+                        .set_name(patch.name.as_deref())
+                        .set_description(patch.description.as_deref())
+                        .set_tags(split(patch.tags))
+                        .set_flags(&split(patch.flags));
+                    match (edit, vault.get_href(&auth, id)) {
+                        (Ok(edit), Ok(href)) => match vault.edit_link(&auth, &href, &edit) {
+                            Ok(Some(link)) => json_output(link),
+                            Ok(None) => Response::empty_404(),
+                            Err(e) => err_response(e)
+                        },
+                        (Err(flag), _) => Response::text(format!("Unknown flag: {}", flag)).with_status_code(400),
+                        _ => Response::empty_404()
+                    }
+                }
+                Err(e) => {
+                    let json = try_or_400::ErrJson::from_err(&e);
+                    Response::json(&json).with_status_code(400)
+                }
+            }
+        },
         (POST) (/links/{id: i64}/read) => {
             match vault.get_href(&auth, id) {
                 Ok(href) => {
@@ -170,13 +534,70 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
                 _ => Response::empty_404()
             }
         },
+        (GET) (/export) => {
+            let query = request.get_param("q").unwrap_or_default();
+            let format = request.get_param("format").unwrap_or_else(|| "json".to_string());
+            let with_queries = request.get_param("queries").is_some_and(|v| v.to_lowercase() == "true");
+            match format.parse::<ExportFormat>() {
+                Ok(format) if with_queries && format != ExportFormat::Json => {
+                    Response::text("Stored queries can be exported only in json format").with_status_code(400)
+                }
+                Ok(format) => match vault.export_links(&auth, &query, format, with_queries) {
+                    Ok(exported) => {
+                        let disposition = format!("attachment; filename=\"links.{}\"", format.extension());
+                        let response = Response::from_data(format.content_type(), exported)
+                            .with_additional_header("Content-Disposition", disposition);
+                        content_encoding::apply(request, response)
+                    }
+                    Err(e) => err_response(e)
+                },
+                Err(_) => Response::text(format!("Unknown format: {}", format)).with_status_code(400)
+            }
+        },
+        (POST) (/import) => {
+            let format = request.get_param("format").map(|f| f.parse::<ImportFormat>());
+            let policy = request.get_param("on_conflict")
+                .map_or(Ok(ConflictPolicy::Overwrite), |p| p.parse::<ConflictPolicy>());
+            let dry_run = request.get_param("dry_run").is_some_and(|v| v.to_lowercase() == "true");
+            let mut contents = String::new();
+            if let Some(mut data) = request.data() {
+                data.read_to_string(&mut contents)?;
+            }
+            match (format.transpose(), policy) {
+                (Ok(format), Ok(policy)) => match vault.import_file(&auth, &contents, format, policy, dry_run) {
+                    Ok(summary) => json_output(summary),
+                    Err(BadImport(e)) => Response::text(e).with_status_code(400),
+                    Err(e) => err_response(e)
+                },
+                (Err(_), _) => Response::text("Unknown format").with_status_code(400),
+                (_, Err(_)) => Response::text("Unknown conflict policy").with_status_code(400)
+            }
+        },
         (GET) (/search) => {
             let query = request.get_param("q").unwrap_or_default();
             let is_stored_query = query.starts_with('@');
-            let fetch_links = |q, v| {
-                match vault.query_links(&auth, q, v, limit) {
-                    Ok((links, _)) => content_encoding::apply(request, json_output(links)),
-                    Err(e) => err_response(e)
+            let fetch_links = |q: String, v: Version| {
+                // plain list of links is returned unless facets or pages were requested.
+
+                if with_facets(request) || request.get_param("cursor").is_some() {
+                    let result = cursor(request).and_then(|cursor| {
+                        let facets = if with_facets(request) {
+                            Some(vault.query_facets(&auth, &q, v.clone())?)
+                        } else {
+                            None
+                        };
+                        let (links, version, next) = vault.query_page(&auth, &q, v, limit.unwrap_or(0), cursor)?;
+                        Ok((links, version, next, facets))
+                    });
+                    match result {
+                        Ok((links, version, next, facets)) => links_response(request, links, version, next, facets),
+                        Err(e) => err_response(e)
+                    }
+                } else {
+                    match vault.query_links(&auth, q, v, limit) {
+                        Ok((links, _)) => content_encoding::apply(request, json_output(links)),
+                        Err(e) => err_response(e)
+                    }
                 }
             };
             if is_stored_query {
@@ -190,7 +611,7 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
                 match vault.find_queries(&auth, chunks.first().unwrap().strip_prefix('@'), lookup) {
                     Ok(queries) => {
                         if !queries.is_empty() && is_exact {
-                            let stored = queries.get(0).map(|q| q.query.clone()).unwrap();
+                            let stored = queries.first().map(|q| q.query.clone()).unwrap();
                             let query = chunks.get(1).unwrap();
                             fetch_links(format!("{} {}", stored, query), version)
                         } else {
@@ -207,3 +628,37 @@ pub fn api_handler(request: &Request, vault: &Vault) -> HandlerResult {
     );
     Ok(resp)
 }
+
+#[cfg(test)]
+mod test_handlers {
+    use super::*;
+    use crate::vault::test_db::{auth, vault};
+    use rstest::*;
+
+    #[rstest]
+    fn test_admin_session(vault: &Vault, auth: Option<Authentication>) {
+        let session = vault.login(&auth, None).unwrap();
+        let users = || {
+            let bearer = format!("Bearer {}", session.token);
+            let request = Request::fake_http(
+                "GET",
+                "/admin/users",
+                vec![("Authorization".to_string(), bearer)],
+                vec![],
+            );
+            api_handler(&request, vault, &TrustedProxies::default())
+                .unwrap()
+                .status_code
+        };
+        let access = Authentication::from_token(Some(&session.token));
+        assert!(matches!(
+            vault.token_user(&session.token),
+            Ok((_, Scope::Write))
+        ));
+        assert!(vault.authorize(&access, Scope::Write).is_ok());
+        assert_eq!(403, users());
+
+        vault.set_admin(&session.login, true).unwrap();
+        assert_eq!(200, users());
+    }
+}
@@ -15,6 +15,7 @@ pub enum JsonError {
     WrongContentType,
 
     /// Could not read the body from the request. Also happens if the body is not valid UTF-8.
+    #[allow(dead_code)]
     IoError(IoError),
 
     /// Error while parsing.
@@ -1,16 +1,18 @@
 mod handlers;
 mod json;
+pub mod proxy;
 mod request;
 mod response;
 
 use log::info;
+use proxy::TrustedProxies;
 use rouille::Response;
 
-pub fn start(vault: super::vault::Vault) {
+pub fn start(vault: super::vault::Vault, proxies: TrustedProxies) {
     info!("Starting a server: http://0.0.0.0:8001");
 
     rouille::start_server("0.0.0.0:8001", move |request| {
-        let res = handlers::api_handler(&request, &vault);
+        let res = handlers::api_handler(request, &vault, &proxies);
         match res {
             Ok(response) => response,
             Err(err) => Response::text(err.to_string()).with_status_code(500),
@@ -0,0 +1,126 @@
+use rouille::Request;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Networks of reverse proxies (like kubernetes ingress) requests are allowed to come through.
+/// Given as comma-separated list of addresses or CIDR blocks, eg. `10.0.0.0/8,127.0.0.1`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrustedProxies {
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl FromStr for TrustedProxies {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let networks = s
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(|network| {
+                let invalid = || format!("Invalid trusted proxy ({}).", network);
+                let mut parts = network.splitn(2, '/');
+                let addr: IpAddr = parts.next().unwrap().parse().map_err(|_| invalid())?;
+                let bits = if addr.is_ipv4() { 32 } else { 128 };
+                let prefix = match parts.next() {
+                    Some(p) => p
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|p| *p <= bits)
+                        .ok_or_else(invalid)?,
+                    None => bits,
+                };
+                Ok((addr, prefix))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(TrustedProxies { networks })
+    }
+}
+
+impl TrustedProxies {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.networks
+            .iter()
+            .any(|(network, prefix)| match (network, ip) {
+                (IpAddr::V4(n), IpAddr::V4(a)) => {
+                    masked(u32::from(*n).into(), *prefix, 32)
+                        == masked(u32::from(*a).into(), *prefix, 32)
+                }
+                (IpAddr::V6(n), IpAddr::V6(a)) => {
+                    masked(u128::from(*n), *prefix, 128) == masked(u128::from(*a), *prefix, 128)
+                }
+                _ => false,
+            })
+    }
+    /// Address of the client who sent a request. Requests which came through trusted proxies
+    /// are attributed to the last address in `X-Forwarded-For` header which is not a trusted
+    /// proxy itself, as any address before it might have been forged by the client.
+    pub fn client_addr(&self, request: &Request) -> IpAddr {
+        let mut addr = request.remote_addr().ip();
+        if !self.contains(&addr) {
+            return addr;
+        }
+        let forwarded = request.header("X-Forwarded-For").unwrap_or_default();
+        for hop in forwarded.rsplit(',') {
+            match hop.trim().parse::<IpAddr>() {
+                Ok(ip) => addr = ip,
+                Err(_) => break,
+            }
+            if !self.contains(&addr) {
+                break;
+            }
+        }
+        addr
+    }
+}
+
+fn masked(addr: u128, prefix: u8, bits: u8) -> u128 {
+    match bits - prefix {
+        0 => addr,
+        128 => 0,
+        n => addr >> n,
+    }
+}
+
+#[cfg(test)]
+mod test_proxy {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("", "10.0.0.5", None, "10.0.0.5")]
+    #[case("10.0.0.0/8", "192.168.1.1", Some("1.2.3.4"), "192.168.1.1")]
+    #[case("10.0.0.0/8", "10.0.0.5", None, "10.0.0.5")]
+    #[case("10.0.0.0/8", "10.0.0.5", Some("1.2.3.4"), "1.2.3.4")]
+    #[case(
+        "10.0.0.0/8",
+        "10.0.0.5",
+        Some("6.6.6.6, 1.2.3.4, 10.1.1.1"),
+        "1.2.3.4"
+    )]
+    #[case("10.0.0.0/8,::1", "::1", Some("10.2.2.2, 1.2.3.4"), "1.2.3.4")]
+    #[case("10.0.0.0/8", "10.0.0.5", Some("garbage, 10.1.1.1"), "10.1.1.1")]
+    fn test_client_addr(
+        #[case] trusted: &str,
+        #[case] remote: &str,
+        #[case] forwarded: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        let trusted: TrustedProxies = trusted.parse().unwrap();
+        let headers = forwarded
+            .map(|f| vec![("X-Forwarded-For".to_string(), f.to_string())])
+            .unwrap_or_default();
+        let remote = (remote.parse::<IpAddr>().unwrap(), 40000).into();
+        let request = Request::fake_http_from(remote, "POST", "/auth", headers, vec![]);
+
+        assert_eq!(expected, trusted.client_addr(&request).to_string());
+    }
+
+    #[rstest]
+    #[case("10.0.0.0/33")]
+    #[case("10.0.0")]
+    #[case("localhost")]
+    fn test_invalid_proxies(#[case] trusted: &str) {
+        assert!(trusted.parse::<TrustedProxies>().is_err());
+    }
+}
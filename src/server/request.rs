@@ -6,6 +6,23 @@ pub struct AuthRequest {
     pub password: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct NewUserRequest {
+    pub login: String,
+    pub password: String,
+    pub admin: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PasswordRequest {
+    pub password: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct QueryRequest {
     pub name: String,
@@ -25,4 +42,50 @@ pub struct LinkPostData {
     pub description: String,
     pub tags: Option<String>,
     pub flags: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub read_at: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LinkPatchData {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub tags: Option<String>,
+    pub flags: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BulkRequest {
+    pub query: String,
+    pub action: String,
+    pub tags: Option<String>,
+    pub flags: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QueryRenameRequest {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TagRenameRequest {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TagMergeRequest {
+    pub from: Vec<String>,
+    pub into: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TagAliasRequest {
+    pub alias: String,
+    pub tag: String,
 }
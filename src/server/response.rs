@@ -1,5 +1,7 @@
 use crate::db::DBError;
-use crate::db::DBError::{Unauthenticated, UnknownUser};
+use crate::db::DBError::{BadQuery, Forbidden, LockedOut, NotAdmin, Unauthenticated, UnknownUser};
+use crate::server::json::json_output;
+use crate::vault::facets::Facets;
 use crate::vault::link::Link;
 
 use log::error;
@@ -10,6 +12,27 @@ use rouille::{Response, ResponseBody};
 pub struct LinksResponse {
     pub version: i32,
     pub links: Vec<Link>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FacetedLinksResponse {
+    pub version: i32,
+    pub links: Vec<Link>,
+    pub next_cursor: Option<String>,
+    pub facets: Facets,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LockedOutResponse {
+    pub error: String,
+    pub retry_after: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct QueryErrorResponse {
+    pub error: String,
+    pub position: usize,
 }
 
 pub fn empty_40x(code: u16) -> Response {
@@ -26,6 +49,19 @@ pub fn err_response(err: DBError) -> Response {
     match err {
         UnknownUser => empty_40x(403),
         Unauthenticated => empty_40x(401),
+        Forbidden => Response::text(Forbidden.to_string()).with_status_code(403),
+        NotAdmin => Response::text(NotAdmin.to_string()).with_status_code(403),
+        LockedOut(secs) => json_output(LockedOutResponse {
+            error: LockedOut(secs).to_string(),
+            retry_after: secs,
+        })
+        .with_status_code(429)
+        .with_additional_header("Retry-After", secs.to_string()),
+        BadQuery(e) => json_output(QueryErrorResponse {
+            error: e.to_string(),
+            position: e.position,
+        })
+        .with_status_code(400),
         e => Response::text(e.to_string()).with_status_code(400),
     }
 }
@@ -2,6 +2,7 @@ use rand::Rng;
 use rpassword::read_password;
 use std::fs::File;
 use std::io::{stdout, BufReader, Read, Write as IoWrite};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const KEY_CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
                              abcdefghijklmnopqrstuvwxyz\
@@ -32,10 +33,9 @@ pub fn read_file(filepath: &str) -> String {
     let file = File::open(filepath).expect("Could not open file");
     let mut buffered_reader = BufReader::new(file);
     let mut contents = String::new();
-    let _number_of_bytes: usize = match buffered_reader.read_to_string(&mut contents) {
-        Ok(number_of_bytes) => number_of_bytes,
-        Err(_err) => 0,
-    };
+    let _number_of_bytes: usize = buffered_reader
+        .read_to_string(&mut contents)
+        .unwrap_or_default();
     contents
 }
 
@@ -84,10 +84,41 @@ pub fn path(url: &str) -> String {
     })
 }
 
+/// Quotes value if it contains characters special to CSV.
+pub fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Extracts host (domain) out of url, without port and leading `www.`. Url without
+/// a scheme is considered to start with a host.
+pub fn host(url: &str) -> String {
+    let path = path(url.trim());
+    let authority = path.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default().to_lowercase();
+    host.strip_prefix("www.").map(String::from).unwrap_or(host)
+}
+
+/// Checks if tag matches expected one. Tags form a hierarchy with `/` as a separator,
+/// so that expected tag ending with `/*` (like `lang/*`) matches the tag itself (`lang`)
+/// and all its descendants (`lang/rust`, `lang/rust/async`).
+pub fn matches_tag(tag: &str, expected: &str) -> bool {
+    match expected.strip_suffix("/*") {
+        Some(parent) => tag
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/')),
+        None => tag == expected,
+    }
+}
+
 pub fn every(elements: &str, expected: &str) -> bool {
     let v: Vec<&str> = elements.split(',').collect();
     for e in expected.split(',') {
-        if !v.iter().any(|&v| v == e) {
+        if !v.iter().any(|t| matches_tag(t, e)) {
             return false;
         }
     }
@@ -97,9 +128,109 @@ pub fn every(elements: &str, expected: &str) -> bool {
 pub fn some(elements: &str, expected: &str) -> bool {
     let v: Vec<&str> = elements.split(',').collect();
     for e in expected.split(',') {
-        if v.iter().any(|&v| v == e) {
+        if v.iter().any(|t| matches_tag(t, e)) {
             return true;
         }
     }
     false
 }
+
+/// Current number of seconds since Unix epoch.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Converts datetime into a number of seconds since Unix epoch. Both SQLite (`YYYY-MM-DD HH:MM:SS`)
+/// and ISO 8601 in UTC (`YYYY-MM-DDTHH:MM:SSZ`) formats are accepted, with time being optional.
+pub fn unix_timestamp(datetime: &str) -> Option<i64> {
+    let datetime = datetime.trim();
+    let datetime = datetime.strip_suffix('Z').unwrap_or(datetime);
+    let (date, time) = datetime.split_once([' ', 'T']).unwrap_or((datetime, ""));
+    let date: Vec<i64> = date
+        .split('-')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    let time: Vec<i64> = if time.is_empty() {
+        vec![0, 0, 0]
+    } else {
+        time.split(':')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .ok()?
+    };
+    match (date.as_slice(), time.as_slice()) {
+        // values out of range are refused before any arithmetic, so they can't overflow.
+        ([y, m, d], [hh, mm, ss])
+            if (1..=9999).contains(y)
+                && (1..=12).contains(m)
+                && (1..=31).contains(d)
+                && (0..24).contains(hh)
+                && (0..60).contains(mm)
+                && (0..60).contains(ss) =>
+        {
+            let days = days_from_civil(*y, *m, *d);
+
+            // dates like 2024-02-30 do not exist, even though they can be converted.
+            if civil_from_days(days) == (*y, *m, *d) {
+                Some(days * 86400 + hh * 3600 + mm * 60 + ss)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Validates and converts datetime (in any of formats accepted by [`unix_timestamp`]) into
+/// SQLite datetime format.
+pub fn normalize_datetime(datetime: &str) -> Option<String> {
+    unix_timestamp(datetime).map(self::datetime)
+}
+
+/// Converts a number of seconds since Unix epoch into SQLite datetime (`YYYY-MM-DD HH:MM:SS`).
+pub fn datetime(timestamp: i64) -> String {
+    let (days, secs) = (timestamp.div_euclid(86400), timestamp.rem_euclid(86400));
+    let (y, m, d) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        y,
+        m,
+        d,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (
+        if m <= 2 {
+            yoe + era * 400 + 1
+        } else {
+            yoe + era * 400
+        },
+        m,
+        d,
+    )
+}
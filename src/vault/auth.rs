@@ -7,16 +7,15 @@ use crate::vault::Vault;
 use bcrypt::verify;
 use clap::ArgMatches;
 use log::debug;
-use miniserde::Serialize;
 use rusqlite::params;
 
 #[derive(Debug)]
 pub struct ApiKey(String);
 
-#[derive(Clone, Serialize, Debug)]
-pub struct UserInfo {
-    pub login: String,
-    pub token: String,
+impl ApiKey {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
 pub enum Authentication {
@@ -47,12 +46,12 @@ impl Vault {
                 debug!("Authenticating with credentials ({}).", login);
                 self.get_connection()
                     .query_row(
-                        "SELECT id, login, password FROM users WHERE login = ?1",
+                        "SELECT id, login, password FROM users WHERE login = ?1 AND NOT is_disabled",
                         params![login],
                         |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
                     )
                     .map_or(Err(UnknownUser), |user: (i64, String, String)| {
-                        if verify(&password, &user.2).unwrap_or(false) {
+                        if verify(password, &user.2).unwrap_or(false) {
                             Ok(User::new(user.0, &user.1))
                         } else {
                             Err(BadPassword)
@@ -61,32 +60,8 @@ impl Vault {
             }
             Authentication::Token(token) => {
                 debug!("Authenticating with token.");
-                self.get_connection()
-                    .query_row(
-                        "SELECT id, login FROM users WHERE api_key = ?1",
-                        params![token.0],
-                        |row| Ok((row.get(0)?, row.get(1)?)),
-                    )
-                    .map_or(Err(UnknownUser), |user: (i64, String)| {
-                        Ok(User::new(user.0, &user.1))
-                    })
+                self.token_user(&token.0).map(|(user, _)| user)
             }
         })
     }
-
-    pub fn user_info(&self, auth: &Option<Authentication>) -> DBResult<UserInfo> {
-        let user = self.authenticate_user(auth)?;
-        self.get_connection()
-            .query_row(
-                "SELECT api_key FROM users WHERE id = ?1",
-                params![user.id],
-                |row| row.get(0),
-            )
-            .map_or(Err(UnknownUser), |token| {
-                Ok(UserInfo {
-                    login: user.login,
-                    token,
-                })
-            })
-    }
 }
@@ -0,0 +1,228 @@
+use crate::db::DBError::BadQuery;
+use crate::db::DBResult;
+use crate::vault::auth::Authentication;
+use crate::vault::link::{LinkEdit, Version};
+use crate::vault::search::{self, Flag};
+use crate::vault::tags::Tag;
+use crate::vault::Vault;
+
+use miniserde::Serialize;
+use rusqlite::params;
+
+/// Action applied to all the links matching a query.
+#[derive(Clone, Debug)]
+pub enum BulkAction {
+    /// Adds or removes tags, sets or clears flags
+    Edit(LinkEdit),
+    /// Renames a tag (from, to) of matching links
+    RenameTag(Tag, Tag),
+    /// Marks links as read
+    Read,
+    /// Deletes links
+    Delete,
+}
+
+/// Ids of links affected by bulk action along with the version they have been stored with.
+#[derive(Serialize, Clone, Debug)]
+pub struct BulkResult {
+    pub version: i32,
+    pub ids: Vec<i64>,
+}
+
+impl BulkResult {
+    pub fn new(version: Version, ids: Vec<i64>) -> Self {
+        BulkResult {
+            version: version.offset(),
+            ids,
+        }
+    }
+}
+
+impl Vault {
+    /// Applies an action to all the user's links matching given query (stored queries included).
+    ///
+    /// All the changes are made in a single transaction and get a single new version. Links which
+    /// wouldn't change are left intact. Dry run reports links which would be affected without
+    /// changing anything.
+    pub fn bulk_links(
+        &self,
+        auth: &Option<Authentication>,
+        query: &str,
+        action: &BulkAction,
+        dry_run: bool,
+    ) -> DBResult<BulkResult> {
+        let user = self.authenticate_user(auth)?;
+        let search = search::parse(&self.expand_query(auth, query)?).map_err(BadQuery)?;
+        let (links, _) = self.find_links(auth, &search, Version::unknown(), None)?;
+
+        let mut conn = self.get_connection();
+        let txn = conn.transaction()?;
+        let latest = self.latest_version_in(&user, &txn)?;
+        let version = latest.bump();
+        let mut ids = Vec::new();
+
+        for link in links {
+            // links shared by other users are matched as well, but they cannot be changed.
+            let link = match self.find_own_link(&link.href, &user, &txn)? {
+                Some(own) if own.id == link.id => own,
+                _ => continue,
+            };
+            let id = link.id.unwrap();
+            match action {
+                BulkAction::Edit(edit) => {
+                    let edited = edit.apply(link.clone());
+                    if edited.hash == link.hash {
+                        continue;
+                    }
+                    self.store_link(edited, version.clone(), &user, &txn)?;
+                }
+                BulkAction::RenameTag(from, to) => {
+                    let tags = link.tags.clone().unwrap_or_default();
+                    if from == to || !tags.contains(from) {
+                        continue;
+                    }
+                    let edit = LinkEdit::default()
+                        .set_tags(vec![format!("-{}", from), format!("+{}", to)]);
+                    self.store_link(edit.apply(link), version.clone(), &user, &txn)?;
+                }
+                BulkAction::Read => {
+                    if !link.toread && link.read_at.is_some() {
+                        continue;
+                    }
+                    let edit = LinkEdit {
+                        flags: vec![(Flag::ToRead, false)],
+                        ..Default::default()
+                    };
+                    self.store_link(edit.apply(link), version.clone(), &user, &txn)?;
+                    txn.execute(
+                        "UPDATE links SET read_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                        params![id],
+                    )?;
+                }
+                BulkAction::Delete => {
+                    txn.execute(
+                        "UPDATE links SET deleted_at = CURRENT_TIMESTAMP, version = ?1 WHERE id = ?2",
+                        params![version.offset(), id],
+                    )?;
+                }
+            }
+            ids.push(id);
+        }
+        if dry_run {
+            txn.rollback()?;
+        } else {
+            txn.commit()?;
+        }
+        Ok(BulkResult::new(
+            if ids.is_empty() { latest } else { version },
+            ids,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test_bulk {
+    #![allow(unused_must_use)]
+
+    use super::*;
+    use crate::vault::test_db::{add_tagged, auth, tags_of, vault};
+    use rstest::*;
+
+    #[rstest]
+    fn test_bulk_edit(vault: &Vault, auth: Option<Authentication>) {
+        let rust = add_tagged(vault, &auth, "http://rust.io", &["rust"])
+            .id
+            .unwrap();
+        let async_rust = add_tagged(vault, &auth, "http://async.io", &["rust", "async"])
+            .id
+            .unwrap();
+        add_tagged(vault, &auth, "http://go.io", &["go"]);
+
+        let edit = LinkEdit::default()
+            .set_tags(vec!["+lang".into(), "-async".into()])
+            .set_flags(&["fav"])
+            .unwrap();
+        let result = vault
+            .bulk_links(&auth, "tags:rust", &BulkAction::Edit(edit), false)
+            .unwrap();
+
+        assert_eq!(vec![rust, async_rust], sorted(result.ids.clone()));
+        assert_eq!(
+            vec!["lang", "rust"],
+            tags_of(vault, &auth, "http://async.io")
+        );
+        assert_eq!(vec!["go"], tags_of(vault, &auth, "http://go.io"));
+
+        // all the changed links share the very same version
+        let (links, _) = vault
+            .query_links(&auth, "", Version::new(result.version), None)
+            .unwrap();
+        assert_eq!(2, links.len());
+        assert!(links.iter().all(|l| l.favourite));
+    }
+
+    #[rstest]
+    fn test_bulk_rename_tag(vault: &Vault, auth: Option<Authentication>) {
+        let js = add_tagged(vault, &auth, "http://js.io", &["js", "web"])
+            .id
+            .unwrap();
+        add_tagged(vault, &auth, "http://css.io", &["web"]);
+
+        let action = BulkAction::RenameTag("js".into(), "javascript".into());
+        let result = vault.bulk_links(&auth, "", &action, false).unwrap();
+
+        assert_eq!(vec![js], result.ids);
+        assert_eq!(
+            vec!["javascript", "web"],
+            tags_of(vault, &auth, "http://js.io")
+        );
+    }
+
+    #[rstest]
+    fn test_bulk_read_and_delete(vault: &Vault, auth: Option<Authentication>) {
+        let id = add_tagged(vault, &auth, "http://later.io", &["later"])
+            .id
+            .unwrap();
+
+        let result = vault
+            .bulk_links(&auth, "tags:later", &BulkAction::Read, false)
+            .unwrap();
+        let link = vault.get_link(&auth, "http://later.io").unwrap().unwrap();
+
+        assert_eq!(vec![id], result.ids);
+        assert!(!link.toread && link.read_at.is_some());
+
+        // links already read are left intact
+        let again = vault
+            .bulk_links(&auth, "tags:later", &BulkAction::Read, false)
+            .unwrap();
+        assert!(again.ids.is_empty());
+        assert_eq!(result.version, again.version);
+
+        let result = vault
+            .bulk_links(&auth, "tags:later", &BulkAction::Delete, false)
+            .unwrap();
+
+        assert_eq!(vec![id], result.ids);
+        assert!(vault.get_link(&auth, "http://later.io").unwrap().is_none());
+    }
+
+    #[rstest]
+    fn test_bulk_dry_run(vault: &Vault, auth: Option<Authentication>) {
+        let id = add_tagged(vault, &auth, "http://dry.io", &["dry"])
+            .id
+            .unwrap();
+
+        let result = vault
+            .bulk_links(&auth, "tags:dry", &BulkAction::Delete, true)
+            .unwrap();
+
+        assert_eq!(vec![id], result.ids);
+        assert!(vault.get_link(&auth, "http://dry.io").unwrap().is_some());
+    }
+
+    fn sorted(mut ids: Vec<i64>) -> Vec<i64> {
+        ids.sort_unstable();
+        ids
+    }
+}
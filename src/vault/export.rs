@@ -0,0 +1,295 @@
+use crate::db::{DBLookupType, DBResult};
+use crate::utils::{escape_csv, unix_timestamp};
+use crate::vault::auth::Authentication;
+use crate::vault::link::{Link, Version};
+use crate::vault::stored_query::StoredQuery;
+use crate::vault::Vault;
+
+use miniserde::{json, Serialize};
+use std::str::FromStr;
+
+/// Format of exported links.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    /// Netscape bookmark file, understood by browsers and Pinboard
+    Netscape,
+    /// JSON array of links (optionally along with stored queries), the same one `import` accepts
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "html" | "netscape" => Ok(ExportFormat::Netscape),
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Netscape => "text/html; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Netscape => "html",
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+    pub fn render(&self, links: &[Link]) -> String {
+        match self {
+            ExportFormat::Netscape => netscape(links),
+            ExportFormat::Json => json::to_string(&links),
+            ExportFormat::Csv => csv(links),
+        }
+    }
+}
+
+impl Vault {
+    /// Exports all the links matching given query (stored queries included) in requested format.
+    ///
+    /// With `with_queries` set, JSON export becomes an object with both `links` and all
+    /// the user's stored `queries`. Other formats have no place for stored queries.
+    pub fn export_links(
+        &self,
+        auth: &Option<Authentication>,
+        query: &str,
+        format: ExportFormat,
+        with_queries: bool,
+    ) -> DBResult<String> {
+        let expanded = self.expand_query(auth, query)?;
+        let (links, _) = self.query_links(auth, expanded, Version::unknown(), None)?;
+        if with_queries && format == ExportFormat::Json {
+            let queries = self.find_queries(auth, Some(""), DBLookupType::Patterned)?;
+            return Ok(json::to_string(&Exported { links, queries }));
+        }
+        Ok(format.render(&links))
+    }
+}
+
+#[derive(Serialize)]
+struct Exported {
+    links: Vec<Link>,
+    queries: Vec<StoredQuery>,
+}
+
+/// Renders links as a Netscape bookmark file.
+///
+/// Apart from standard attributes, links carry Pinboard-specific `PRIVATE`, `TOREAD` and `TAGS`,
+/// and `FAVOURITE` which is understood by linkify only. Update and read times are exported as
+/// `LAST_MODIFIED` and `LAST_VISIT` respectively.
+fn netscape(links: &[Link]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <!-- This is an automatically generated file.\n     \
+         It will be read and overwritten.\n     \
+         DO NOT EDIT! -->\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n\
+         <H1>Bookmarks</H1>\n\
+         <DL><p>\n",
+    );
+    for link in links {
+        let mut attrs = vec![("HREF", escape_html(&link.href))];
+        let timestamps = [
+            ("ADD_DATE", Some(&link.created_at)),
+            ("LAST_MODIFIED", link.updated_at.as_ref()),
+            ("LAST_VISIT", link.read_at.as_ref()),
+        ];
+        for (attr, ts) in timestamps.iter() {
+            if let Some(ts) = ts.and_then(|t| unix_timestamp(t)) {
+                attrs.push((attr, ts.to_string()));
+            }
+        }
+        attrs.push(("PRIVATE", flag(!link.shared)));
+        attrs.push(("TOREAD", flag(link.toread)));
+        if link.favourite {
+            attrs.push(("FAVOURITE", flag(true)));
+        }
+        attrs.push((
+            "TAGS",
+            escape_html(&link.tags.as_ref().map_or(String::new(), |t| t.join(","))),
+        ));
+
+        let attrs: Vec<_> = attrs
+            .into_iter()
+            .map(|(attr, value)| format!("{}=\"{}\"", attr, value))
+            .collect();
+        out.push_str(&format!(
+            "<DT><A {}>{}</A>\n",
+            attrs.join(" "),
+            escape_html(&link.name)
+        ));
+        if let Some(desc) = link.description.as_ref() {
+            out.push_str(&format!("<DD>{}\n", escape_html(desc)));
+        }
+    }
+    out.push_str("</DL><p>\n");
+    out
+}
+
+/// Renders links as CSV, one link per row. Tags are comma-separated within their column.
+fn csv(links: &[Link]) -> String {
+    let mut out = String::from(
+        "href,name,description,tags,toread,shared,favourite,created_at,updated_at,read_at,hash\n",
+    );
+    for link in links {
+        let row = [
+            escape_csv(&link.href),
+            escape_csv(&link.name),
+            escape_csv(link.description.as_deref().unwrap_or_default()),
+            escape_csv(&link.tags.as_ref().map_or(String::new(), |t| t.join(","))),
+            flag(link.toread),
+            flag(link.shared),
+            flag(link.favourite),
+            escape_csv(&link.created_at),
+            escape_csv(link.updated_at.as_deref().unwrap_or_default()),
+            escape_csv(link.read_at.as_deref().unwrap_or_default()),
+            escape_csv(link.hash.as_deref().unwrap_or_default()),
+        ];
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn flag(value: bool) -> String {
+    if value { "1" } else { "0" }.to_string()
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test_export {
+    #![allow(unused_must_use)]
+
+    use super::*;
+    use crate::vault::import::ConflictPolicy;
+    use crate::vault::test_db::{auth, other, vault};
+    use rstest::*;
+
+    fn link(name: &str, tags: &[&str]) -> Link {
+        Link::new(
+            None,
+            &format!("https://{}.com", name),
+            name,
+            Some("\"quoted\", <b>bold</b> & more"),
+            Some(tags.iter().map(|t| t.to_string()).collect()),
+        )
+        .set_toread(true)
+        .set_favourite(true)
+    }
+
+    #[rstest]
+    fn test_json_round_trip(
+        vault: &Vault,
+        auth: Option<Authentication>,
+        other: Option<Authentication>,
+    ) {
+        vault.add_link(&auth, link("exported", &["rust", "async"]));
+        vault.add_link(&auth, link("skipped", &["python"]));
+
+        let exported = vault
+            .export_links(&auth, "tags:rust", ExportFormat::Json, false)
+            .unwrap();
+        let links: Vec<Link> = json::from_str(&exported).unwrap();
+        assert_eq!(1, links.len());
+
+        vault.import_links(
+            &other,
+            links.iter().cloned().map(Ok).collect(),
+            ConflictPolicy::Overwrite,
+            false,
+        );
+
+        let (imported, _) = vault
+            .query_links(&other, "", Version::unknown(), None)
+            .unwrap();
+        assert_eq!(1, imported.len());
+
+        let (original, copy) = (&links[0], &imported[0]);
+        assert_eq!(original.href, copy.href);
+        assert_eq!(original.name, copy.name);
+        assert_eq!(original.description, copy.description);
+        assert_eq!(original.tags, copy.tags);
+        assert_eq!(
+            (original.toread, original.shared, original.favourite),
+            (copy.toread, copy.shared, copy.favourite)
+        );
+        assert_eq!(original.created_at, copy.created_at);
+        assert_eq!(original.updated_at, copy.updated_at);
+        assert_eq!(original.hash, copy.hash);
+    }
+
+    #[rstest]
+    fn test_json_with_queries_round_trip(
+        vault: &Vault,
+        auth: Option<Authentication>,
+        other: Option<Authentication>,
+    ) {
+        vault.add_link(&auth, link("queried", &["rust"]));
+        vault.store_query(&auth, "rusty".into(), "tags:rust".into());
+
+        let exported = vault
+            .export_links(&auth, "", ExportFormat::Json, true)
+            .unwrap();
+        let summary = vault
+            .import_file(&other, &exported, None, ConflictPolicy::Overwrite, false)
+            .unwrap();
+        assert_eq!((1, 1), (summary.created, summary.queries));
+
+        let queries = vault
+            .find_queries(&other, Some("rusty"), DBLookupType::Exact)
+            .unwrap();
+        assert_eq!("tags:rust", queries[0].query);
+    }
+
+    #[rstest]
+    fn test_netscape_format() {
+        let link = link("netscape", &["b", "a"])
+            .set_timestamp("2024-03-15 10:20:30".to_string())
+            .set_read_at(Some("2024-03-16 00:00:00".to_string()));
+        let html = ExportFormat::Netscape.render(&[link]);
+
+        assert!(html.starts_with("<!DOCTYPE NETSCAPE-Bookmark-file-1>"));
+        assert!(html.contains(
+            "<DT><A HREF=\"https://netscape.com\" ADD_DATE=\"1710498030\" LAST_VISIT=\"1710547200\" \
+             PRIVATE=\"1\" TOREAD=\"1\" FAVOURITE=\"1\" TAGS=\"b,a\">netscape</A>\n"
+        ));
+        assert!(html.contains("<DD>&quot;quoted&quot;, &lt;b&gt;bold&lt;/b&gt; &amp; more\n"));
+    }
+
+    #[rstest]
+    fn test_csv_format() {
+        let link = link("csv", &["rust", "async"]).set_timestamp("2024-03-15 10:20:30".to_string());
+        let hash = link.hash.clone().unwrap();
+        let csv = ExportFormat::Csv.render(&[link]);
+        let rows: Vec<_> = csv.lines().collect();
+
+        assert_eq!(2, rows.len());
+        assert_eq!(
+            format!(
+                "https://csv.com,csv,\"\"\"quoted\"\", <b>bold</b> & more\",\"rust,async\",1,0,1,2024-03-15 10:20:30,,,{}",
+                hash
+            ),
+            rows[1]
+        );
+    }
+}
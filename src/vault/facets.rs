@@ -0,0 +1,153 @@
+use crate::db::DBError::BadQuery;
+use crate::db::DBResult;
+use crate::utils::host;
+use crate::vault::auth::Authentication;
+use crate::vault::link::{Link, Version};
+use crate::vault::search::{self, Search};
+use crate::vault::Vault;
+
+use miniserde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Number of links sharing given value (a tag, a flag or a domain).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u32,
+}
+
+/// Breakdown of links matching a query by their tags, flags and domains.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Facets {
+    pub tags: Vec<FacetCount>,
+    pub flags: Vec<FacetCount>,
+    pub domains: Vec<FacetCount>,
+}
+
+impl Facets {
+    /// Counts facets of given links. Deleted links (tombstones) are not counted.
+    pub fn new(links: &[Link]) -> Self {
+        let mut tags = HashMap::new();
+        let mut domains = HashMap::new();
+        let mut flags = [("toread", 0), ("favourite", 0), ("shared", 0)];
+
+        for link in links.iter().filter(|l| !l.deleted) {
+            for tag in link.tags.iter().flatten() {
+                *tags.entry(tag.clone()).or_insert(0) += 1;
+            }
+            *domains.entry(host(&link.href)).or_insert(0) += 1;
+            for (i, is_set) in [link.toread, link.favourite, link.shared]
+                .iter()
+                .enumerate()
+            {
+                flags[i].1 += *is_set as u32;
+            }
+        }
+        Facets {
+            tags: counts(tags),
+            flags: flags
+                .iter()
+                .map(|(flag, count)| FacetCount {
+                    value: flag.to_string(),
+                    count: *count,
+                })
+                .collect(),
+            domains: counts(domains),
+        }
+    }
+}
+
+impl Vault {
+    /// Counts facets of all the links matching search, regardless of any limit, so that
+    /// facets are computed exactly the way [Vault::find_links] finds the links.
+    pub fn find_facets(
+        &self,
+        auth: &Option<Authentication>,
+        search: &Search,
+        version: Version,
+    ) -> DBResult<Facets> {
+        let (links, _) = self.find_links(auth, search, version, None)?;
+        Ok(Facets::new(&links))
+    }
+    pub fn query_facets<S: AsRef<str>>(
+        &self,
+        auth: &Option<Authentication>,
+        query: S,
+        version: Version,
+    ) -> DBResult<Facets> {
+        let search = search::parse(query.as_ref()).map_err(BadQuery)?;
+        self.find_facets(auth, &search, version)
+    }
+}
+
+/// Most frequent values go first.
+fn counts(values: HashMap<String, u32>) -> Vec<FacetCount> {
+    let mut counts: Vec<_> = values
+        .into_iter()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts
+}
+
+#[cfg(test)]
+mod test_facets {
+    #![allow(unused_must_use)]
+
+    use super::*;
+    use crate::vault::test_db::{auth, vault};
+    use rstest::*;
+
+    fn facets(counts: &[FacetCount]) -> Vec<(&str, u32)> {
+        counts.iter().map(|c| (c.value.as_str(), c.count)).collect()
+    }
+
+    #[rstest]
+    fn test_query_facets(vault: &Vault, auth: Option<Authentication>) {
+        let tagged = [
+            (
+                "https://github.com/tokio-rs/tokio",
+                vec!["rust", "async"],
+                true,
+            ),
+            ("https://www.github.com/golang/go", vec!["go"], false),
+            ("https://docs.rs/hyper", vec!["rust", "http"], false),
+            ("https://docs.python.org", vec!["python"], true),
+        ];
+        for (href, tags, toread) in tagged.iter() {
+            vault.add_link(
+                &auth,
+                Link::new(
+                    None,
+                    href,
+                    href,
+                    None,
+                    Some(tags.iter().map(|t| t.to_string()).collect()),
+                )
+                .set_toread(*toread),
+            );
+        }
+        vault.del_link(&auth, "https://docs.python.org");
+
+        let all = vault.query_facets(&auth, "", Version::unknown()).unwrap();
+        assert_eq!(
+            vec![("rust", 2), ("async", 1), ("go", 1), ("http", 1)],
+            facets(&all.tags)
+        );
+        assert_eq!(
+            vec![("github.com", 2), ("docs.rs", 1)],
+            facets(&all.domains)
+        );
+        assert_eq!(
+            vec![("toread", 1), ("favourite", 0), ("shared", 0)],
+            facets(&all.flags)
+        );
+
+        // facets follow the very same tags classification as links do
+        let rust = vault
+            .query_facets(&auth, "tags:rust,-async", Version::unknown())
+            .unwrap();
+        assert_eq!(vec![("http", 1), ("rust", 1)], facets(&rust.tags));
+        assert_eq!(vec![("docs.rs", 1)], facets(&rust.domains));
+    }
+}
@@ -0,0 +1,970 @@
+use crate::utils::{datetime, normalize_datetime};
+use crate::vault::link::Link;
+use crate::vault::stored_query::StoredQuery;
+
+use miniserde::json::{self, Value};
+use miniserde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Format of imported links.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportFormat {
+    /// JSON array of links, or an object with links and stored queries, as exported by linkify
+    Json,
+    /// Netscape bookmark file, exported by browsers and Pinboard
+    Netscape,
+    /// Pinboard JSON backup
+    Pinboard,
+    /// Pocket HTML export
+    Pocket,
+}
+
+impl FromStr for ImportFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" | "linkify" => Ok(ImportFormat::Json),
+            "html" | "netscape" => Ok(ImportFormat::Netscape),
+            "pinboard" => Ok(ImportFormat::Pinboard),
+            "pocket" => Ok(ImportFormat::Pocket),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = match self {
+            ImportFormat::Json => "json",
+            ImportFormat::Netscape => "html",
+            ImportFormat::Pinboard => "pinboard",
+            ImportFormat::Pocket => "pocket",
+        };
+        write!(f, "{}", format)
+    }
+}
+
+/// Problem with a single imported record. Records are numbered from 1.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportError {
+    pub record: usize,
+    pub reason: String,
+}
+
+impl ImportError {
+    pub fn new(record: usize, reason: &str) -> Self {
+        ImportError {
+            record,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "record #{}: {}", self.record, self.reason)
+    }
+}
+
+/// Either a link read from imported file or a reason why it couldn't be read.
+pub type Record = Result<Link, ImportError>;
+
+/// Decides what happens when imported link is already stored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictPolicy {
+    /// Stored link is left intact
+    Skip,
+    /// Stored link is replaced with imported one
+    Overwrite,
+    /// Stored link is left intact, except that imported tags are added to it
+    MergeTags,
+    /// Stored link is replaced only if imported one has been created or updated later
+    Newest,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "merge-tags" => Ok(ConflictPolicy::MergeTags),
+            "newest" => Ok(ConflictPolicy::Newest),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let policy = match self {
+            ConflictPolicy::Skip => "skip",
+            ConflictPolicy::Overwrite => "overwrite",
+            ConflictPolicy::MergeTags => "merge-tags",
+            ConflictPolicy::Newest => "newest",
+        };
+        write!(f, "{}", policy)
+    }
+}
+
+impl ConflictPolicy {
+    /// Resolves a conflict between stored and imported link. Returns a link which should be
+    /// stored instead of existing one, or None if existing one should be left intact.
+    pub fn resolve(&self, existing: &Link, imported: Link) -> Option<Link> {
+        match self {
+            ConflictPolicy::Skip => None,
+            ConflictPolicy::Overwrite => Some(imported),
+            ConflictPolicy::MergeTags => {
+                let mut tags = existing.tags.clone().unwrap_or_default();
+                for tag in imported.tags.unwrap_or_default() {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+                let mut merged = existing.clone();
+                merged.tags = Some(tags);
+                merged.updated_at = None;
+                Some(merged.digest())
+            }
+            ConflictPolicy::Newest => {
+                // links which have never been updated are considered updated when created.
+                let modified = |link: &Link| {
+                    link.updated_at
+                        .clone()
+                        .unwrap_or_else(|| link.created_at.clone())
+                };
+                if modified(&imported) > modified(existing) {
+                    Some(imported)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Imported link along with the outcome of its import: `created`, `updated` or `unchanged`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportedLink {
+    pub href: String,
+    pub status: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ImportSummary {
+    pub created: u32,
+    pub updated: u32,
+    pub unchanged: u32,
+    pub skipped: u32,
+    pub queries: u32,
+    pub links: Vec<ImportedLink>,
+    pub errors: Vec<ImportError>,
+}
+
+impl ImportSummary {
+    pub fn created(&mut self, href: &str) {
+        self.created += 1;
+        self.push(href, "created");
+    }
+    pub fn updated(&mut self, href: &str) {
+        self.updated += 1;
+        self.push(href, "updated");
+    }
+    pub fn unchanged(&mut self, href: &str) {
+        self.unchanged += 1;
+        self.push(href, "unchanged");
+    }
+    pub fn skip(&mut self, error: ImportError) {
+        self.skipped += 1;
+        self.errors.push(error);
+    }
+    fn push(&mut self, href: &str, status: &str) {
+        self.links.push(ImportedLink {
+            href: href.to_string(),
+            status: status.to_string(),
+        });
+    }
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} created, {} updated, {} unchanged, {} skipped",
+            self.created, self.updated, self.unchanged, self.skipped
+        )?;
+        if self.queries > 0 {
+            write!(f, ", {} stored queries", self.queries)?;
+        }
+        Ok(())
+    }
+}
+
+impl ImportFormat {
+    /// Guesses format of imported file by its content.
+    pub fn detect(contents: &str) -> Option<ImportFormat> {
+        let contents = contents.trim_start();
+        if contents.starts_with('{') {
+            return Some(ImportFormat::Json);
+        }
+        if contents.starts_with('[') {
+            // pinboard describes links with "description" and "extended", linkify with "name".
+            return match json::from_str::<Vec<Value>>(contents)
+                .ok()
+                .and_then(|v| v.into_iter().next())
+            {
+                Some(Value::Object(o)) if !o.contains_key("name") && o.contains_key("extended") => {
+                    Some(ImportFormat::Pinboard)
+                }
+                _ => Some(ImportFormat::Json),
+            };
+        }
+        let contents = contents.to_lowercase();
+        if contents.contains("<title>pocket export") {
+            Some(ImportFormat::Pocket)
+        } else if contents.contains("netscape-bookmark-file") || contents.contains("<a ") {
+            Some(ImportFormat::Netscape)
+        } else {
+            None
+        }
+    }
+
+    /// Reads links from imported file. Fails only if file cannot be read at all, problems with
+    /// particular links are reported as erroneous records instead.
+    pub fn parse(&self, contents: &str) -> Result<Vec<Record>, String> {
+        match self {
+            ImportFormat::Json => json_records(&exported(contents, "links"), linkify_link),
+            ImportFormat::Pinboard => json_records(contents, pinboard_link),
+            ImportFormat::Netscape => Ok(netscape(contents)),
+            ImportFormat::Pocket => Ok(pocket(contents)),
+        }
+    }
+}
+
+/// Reads links from imported file of given format, or of detected one if no format was given.
+pub fn read_records(contents: &str, format: Option<ImportFormat>) -> Result<Vec<Record>, String> {
+    match format.or_else(|| ImportFormat::detect(contents)) {
+        Some(format) => format.parse(contents),
+        None => Err("Unrecognized format of imported file".to_string()),
+    }
+}
+
+/// Reads stored queries which linkify exports along with links. Files with no stored queries
+/// (including those in other formats) give no queries.
+pub fn read_queries(contents: &str) -> Vec<StoredQuery> {
+    json::from_str(&exported(contents, "queries")).unwrap_or_default()
+}
+
+/// Picks a field of JSON object linkify exports links and stored queries into.
+/// Any other content is returned as it is.
+fn exported(contents: &str, field: &str) -> String {
+    match json::from_str::<Value>(contents) {
+        Ok(Value::Object(mut o)) if o.contains_key(field) => json::to_string(&o.remove(field)),
+        _ => contents.to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+struct LinkifyRecord {
+    href: String,
+    name: String,
+    description: Option<String>,
+    tags: Option<Vec<String>>,
+    shared: Option<bool>,
+    toread: Option<bool>,
+    favourite: Option<bool>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    read_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PinboardRecord {
+    href: String,
+    description: Option<String>,
+    extended: Option<String>,
+    time: Option<String>,
+    shared: Option<String>,
+    toread: Option<String>,
+    tags: Option<String>,
+}
+
+/// Reads JSON array, converting each of its elements separately, so that one malformed
+/// element doesn't prevent others from being imported.
+fn json_records<T: Deserialize>(
+    contents: &str,
+    convert: fn(usize, T) -> Record,
+) -> Result<Vec<Record>, String> {
+    let values: Vec<Value> =
+        json::from_str(contents).map_err(|_| "Not a JSON array of links".to_string())?;
+
+    Ok(values
+        .iter()
+        .enumerate()
+        .map(
+            |(i, value)| match json::from_str::<T>(&json::to_string(value)) {
+                Ok(record) => convert(i + 1, record),
+                Err(_) => Err(ImportError::new(i + 1, "missing or malformed fields")),
+            },
+        )
+        .collect())
+}
+
+fn linkify_link(record: usize, r: LinkifyRecord) -> Record {
+    let link = Link::new(
+        None,
+        &r.href,
+        &r.name,
+        r.description.as_deref(),
+        r.tags.map(|t| tags(t.iter().map(String::as_str))),
+    )
+    .set_shared(r.shared.unwrap_or_default())
+    .set_toread(r.toread.unwrap_or_default())
+    .set_favourite(r.favourite.unwrap_or_default());
+
+    with_timestamps(
+        record,
+        link,
+        [
+            r.created_at.as_deref(),
+            r.updated_at.as_deref(),
+            r.read_at.as_deref(),
+        ],
+        normalize_datetime,
+    )
+}
+
+fn pinboard_link(record: usize, r: PinboardRecord) -> Record {
+    let name = r.description.filter(|d| !d.trim().is_empty());
+    let description = r.extended.filter(|d| !d.trim().is_empty());
+    let link = Link::new(
+        None,
+        &r.href,
+        name.as_deref().unwrap_or(&r.href),
+        description.as_deref(),
+        r.tags.map(|t| tags(t.split_whitespace())),
+    )
+    .set_shared(r.shared.as_deref() == Some("yes"))
+    .set_toread(r.toread.as_deref() == Some("yes"));
+
+    with_timestamps(
+        record,
+        link,
+        [r.time.as_deref(), None, None],
+        normalize_datetime,
+    )
+}
+
+/// Completes a link with its creation, update and read time, converted with given function.
+/// Record is considered erroneous if link has no url or any of timestamps is invalid.
+fn with_timestamps(
+    record: usize,
+    link: Link,
+    timestamps: [Option<&str>; 3],
+    convert: fn(&str) -> Option<String>,
+) -> Record {
+    if link.href.trim().is_empty() {
+        return Err(ImportError::new(record, "missing url"));
+    }
+    let mut converted = [None, None, None];
+    for (i, name) in ["creation", "update", "read"].iter().enumerate() {
+        if let Some(ts) = timestamps[i].filter(|t| !t.trim().is_empty()) {
+            converted[i] = Some(convert(ts).ok_or_else(|| {
+                ImportError::new(record, &format!("invalid {} time '{}'", name, ts))
+            })?);
+        }
+    }
+    let [created_at, updated_at, read_at] = converted;
+    Ok(link
+        .set_timestamp(created_at.unwrap_or_default())
+        .set_updated_at(updated_at)
+        .set_read_at(read_at)
+        .digest())
+}
+
+fn from_unix(ts: &str) -> Option<String> {
+    ts.trim().parse::<i64>().ok().map(datetime)
+}
+
+/// Trims tags, dropping empty and duplicated ones.
+fn tags<'a, I: Iterator<Item = &'a str>>(input: I) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Link being read from HTML, until all of its parts (like description) are collected.
+struct Anchor {
+    attrs: Vec<(String, String)>,
+    name: String,
+    description: Option<String>,
+    tags: Vec<String>,
+}
+
+impl Anchor {
+    fn new(attrs: Vec<(String, String)>, tags: Vec<String>) -> Self {
+        Anchor {
+            attrs,
+            name: String::new(),
+            description: None,
+            tags,
+        }
+    }
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+    fn into_link(self, record: usize, timestamps: [&str; 3]) -> Record {
+        let href = self.attr("href").unwrap_or_default().trim();
+        let name = self.name.trim();
+        let description = self.description.as_deref().map(str::trim);
+        let mut tags = self.tags.clone();
+        if let Some(t) = self.attr("tags") {
+            tags.extend(t.split(',').map(String::from));
+        }
+        let link = Link::new(
+            None,
+            href,
+            if name.is_empty() { href } else { name },
+            description.filter(|d| !d.is_empty()),
+            Some(self::tags(tags.iter().map(String::as_str))),
+        )
+        .set_shared(self.attr("private") == Some("0"))
+        .set_toread(self.attr("toread") == Some("1"))
+        .set_favourite(self.attr("favourite") == Some("1"));
+
+        let [created, updated, read] = timestamps;
+        with_timestamps(
+            record,
+            link,
+            [self.attr(created), self.attr(updated), self.attr(read)],
+            from_unix,
+        )
+    }
+}
+
+/// Reads Netscape bookmark file. Links are grouped in folders which become tags of all
+/// the links they contain (including links of nested folders).
+fn netscape(contents: &str) -> Vec<Record> {
+    enum Scope {
+        Other,
+        Folder,
+        Anchor,
+        Description,
+    }
+    let mut records = Vec::new();
+    let mut folders: Vec<Option<String>> = Vec::new();
+    let mut folder: Option<String> = None;
+    let mut anchor: Option<Anchor> = None;
+    let mut scope = Scope::Other;
+
+    let flush = |anchor: &mut Option<Anchor>, records: &mut Vec<Record>| {
+        if let Some(a) = anchor.take() {
+            let record = records.len() + 1;
+            records.push(a.into_link(record, ["add_date", "last_modified", "last_visit"]));
+        }
+    };
+    for node in html_nodes(contents) {
+        match node {
+            Node::Open(tag, attrs) => match tag.as_str() {
+                "h3" => {
+                    flush(&mut anchor, &mut records);
+                    folder = Some(String::new());
+                    scope = Scope::Folder;
+                }
+                "dl" => {
+                    flush(&mut anchor, &mut records);
+                    folders.push(folder.take());
+                    scope = Scope::Other;
+                }
+                "a" => {
+                    flush(&mut anchor, &mut records);
+                    let tags = folders.iter().flatten().map(|f| f.trim().to_string());
+                    anchor = Some(Anchor::new(attrs, tags.collect()));
+                    scope = Scope::Anchor;
+                }
+                "dd" => {
+                    if let Some(a) = anchor.as_mut() {
+                        a.description = Some(String::new());
+                        scope = Scope::Description;
+                    }
+                }
+                "dt" => {
+                    flush(&mut anchor, &mut records);
+                    scope = Scope::Other;
+                }
+                _ => (),
+            },
+            Node::Close(tag) => match tag.as_str() {
+                "dl" => {
+                    flush(&mut anchor, &mut records);
+                    folders.pop();
+                    scope = Scope::Other;
+                }
+                "h3" | "a" => scope = Scope::Other,
+                _ => (),
+            },
+            Node::Text(text) => match scope {
+                Scope::Folder => folder.get_or_insert_with(String::new).push_str(&text),
+                Scope::Anchor => {
+                    if let Some(a) = anchor.as_mut() {
+                        a.name.push_str(&text)
+                    }
+                }
+                Scope::Description => {
+                    if let Some(d) = anchor.as_mut().and_then(|a| a.description.as_mut()) {
+                        d.push_str(&text)
+                    }
+                }
+                Scope::Other => (),
+            },
+        }
+    }
+    flush(&mut anchor, &mut records);
+    records
+}
+
+/// Reads Pocket export, which lists unread links first, followed by archived (read) ones.
+fn pocket(contents: &str) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut heading: Option<String> = None;
+    let mut archived = false;
+    let mut anchor: Option<Anchor> = None;
+
+    for node in html_nodes(contents) {
+        match node {
+            Node::Open(tag, attrs) => match tag.as_str() {
+                "h1" => heading = Some(String::new()),
+                "a" => anchor = Some(Anchor::new(attrs, Vec::new())),
+                _ => (),
+            },
+            Node::Close(tag) => match tag.as_str() {
+                "h1" => {
+                    archived = heading
+                        .take()
+                        .is_some_and(|h| h.to_lowercase().contains("archive"))
+                }
+                "a" => {
+                    if let Some(a) = anchor.take() {
+                        let record = records.len() + 1;
+                        records.push(
+                            a.into_link(record, ["time_added", "time_updated", "time_read"])
+                                .map(|link| link.set_toread(!archived)),
+                        );
+                    }
+                }
+                _ => (),
+            },
+            Node::Text(text) => {
+                if let Some(a) = anchor.as_mut() {
+                    a.name.push_str(&text)
+                } else if let Some(h) = heading.as_mut() {
+                    h.push_str(&text)
+                }
+            }
+        }
+    }
+    records
+}
+
+#[derive(Debug, PartialEq)]
+enum Node {
+    /// Opening tag with its (lowercased) name and attributes
+    Open(String, Vec<(String, String)>),
+    /// Closing tag with its (lowercased) name
+    Close(String),
+    /// Text between tags, with entities decoded
+    Text(String),
+}
+
+/// Splits HTML into a flat list of tags and texts. Bookmark files are rarely well-formed
+/// (Netscape format doesn't even close `<DT>` and `<p>` tags), so no tree is built.
+fn html_nodes(html: &str) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        let text = &rest[..start];
+        if !text.trim().is_empty() {
+            nodes.push(Node::Text(unescape_html(text)));
+        }
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let end = tag_end(rest);
+        let tag = &rest[1..end];
+        rest = rest.get(end + 1..).unwrap_or_default();
+
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+        match tag.strip_prefix('/') {
+            Some(name) => nodes.push(Node::Close(name.trim().to_lowercase())),
+            None => {
+                let tag = tag.trim_end_matches('/');
+                let (name, attrs) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+                nodes.push(Node::Open(name.to_lowercase(), html_attrs(attrs)))
+            }
+        }
+    }
+    if !rest.trim().is_empty() {
+        nodes.push(Node::Text(unescape_html(rest)));
+    }
+    nodes
+}
+
+/// Finds closing `>` of a tag, skipping the ones within quoted attribute values.
+fn tag_end(tag: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (c, quote) {
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('>', None) => return i,
+            _ => (),
+        }
+    }
+    tag.len()
+}
+
+fn html_attrs(attrs: &str) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    let mut chars = attrs.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == '=' {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+        if name.is_empty() {
+            if chars.next().is_none() {
+                break;
+            }
+            continue;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.peek().copied() {
+                Some(q) if q == '"' || q == '\'' => {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if c == q {
+                            break;
+                        }
+                        value.push(c);
+                    }
+                }
+                _ => {
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() {
+                            break;
+                        }
+                        value.push(c);
+                        chars.next();
+                    }
+                }
+            }
+        }
+        result.push((name.to_lowercase(), unescape_html(&value)));
+    }
+    result
+}
+
+fn unescape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| (&rest[1..end + 1], end + 2));
+        let decoded = entity.and_then(|(name, len)| {
+            let c = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => match name.strip_prefix('#') {
+                    Some(code) => match code.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => code.parse::<u32>().ok(),
+                    }
+                    .and_then(char::from_u32),
+                    None => None,
+                },
+            };
+            c.map(|c| (c, len))
+        });
+        match decoded {
+            Some((c, len)) => {
+                result.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod test_import {
+    use super::*;
+    use crate::vault::export::ExportFormat;
+    use rstest::*;
+
+    fn links(records: Vec<Record>) -> Vec<Link> {
+        records.into_iter().map(Result::unwrap).collect()
+    }
+
+    #[rstest]
+    #[case(
+        "[{\"href\": \"https://foo.com\", \"name\": \"foo\"}]",
+        Some(ImportFormat::Json)
+    )]
+    #[case(
+        "[{\"href\": \"https://foo.com\", \"description\": \"foo\", \"extended\": \"\"}]",
+        Some(ImportFormat::Pinboard)
+    )]
+    #[case(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n<DL><p>",
+        Some(ImportFormat::Netscape)
+    )]
+    #[case(
+        "<html><head><title>Pocket Export</title></head></html>",
+        Some(ImportFormat::Pocket)
+    )]
+    #[case("href,name,description", None)]
+    fn test_detect_format(#[case] contents: &str, #[case] expected: Option<ImportFormat>) {
+        assert_eq!(expected, ImportFormat::detect(contents));
+    }
+
+    #[rstest]
+    fn test_netscape_folders_become_tags() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3 ADD_DATE="1600000000">Programming</H3>
+    <DL><p>
+        <DT><H3>Rust &amp; co</H3>
+        <DL><p>
+            <DT><A HREF="https://tokio.rs" ADD_DATE="1710498030" TAGS="async">Tokio</A>
+            <DD>Asynchronous <b>runtime</b>
+        </DL><p>
+        <DT><A HREF="https://python.org" PRIVATE="0" TOREAD="1">Python</A>
+    </DL><p>
+    <DT><A HREF="https://example.com"></A>
+</DL><p>"#;
+        let links = links(ImportFormat::Netscape.parse(html).unwrap());
+
+        assert_eq!(3, links.len());
+        assert_eq!("Tokio", links[0].name);
+        assert_eq!(
+            Some("Asynchronous runtime"),
+            links[0].description.as_deref()
+        );
+        assert_eq!(
+            Some(vec![
+                "Programming".into(),
+                "Rust & co".into(),
+                "async".into()
+            ]),
+            links[0].tags
+        );
+        assert_eq!("2024-03-15 10:20:30", links[0].created_at);
+        assert_eq!(Some(vec!["Programming".to_string()]), links[1].tags);
+        assert!(links[1].shared && links[1].toread);
+        assert_eq!("https://example.com", links[2].name);
+        assert_eq!(Some(vec![]), links[2].tags);
+    }
+
+    #[rstest]
+    fn test_netscape_record_errors() {
+        let html = r#"<DL><p>
+<DT><A ADD_DATE="1710498030">No url</A>
+<DT><A HREF="https://foo.com" ADD_DATE="yesterday">Foo</A>
+<DT><A HREF="https://bar.com">Bar</A>
+</DL>"#;
+        let records = ImportFormat::Netscape.parse(html).unwrap();
+
+        assert_eq!(3, records.len());
+        assert_eq!(
+            &ImportError::new(1, "missing url"),
+            records[0].as_ref().unwrap_err()
+        );
+        assert_eq!(
+            &ImportError::new(2, "invalid creation time 'yesterday'"),
+            records[1].as_ref().unwrap_err()
+        );
+        assert!(records[2].is_ok());
+    }
+
+    #[rstest]
+    fn test_netscape_round_trip() {
+        let link = Link::new(
+            None,
+            "https://foo.com/?a=1&b=\"2\"",
+            "<Foo> & bar",
+            Some("multi\nline"),
+            Some(vec!["rust".into(), "web".into()]),
+        )
+        .set_shared(true)
+        .set_favourite(true)
+        .set_timestamp("2024-03-15 10:20:30".into())
+        .set_read_at(Some("2024-03-16 00:00:00".into()))
+        .digest();
+
+        let html = ExportFormat::Netscape.render(std::slice::from_ref(&link));
+        let imported = links(ImportFormat::Netscape.parse(&html).unwrap());
+
+        assert_eq!(1, imported.len());
+        assert_eq!(link.href, imported[0].href);
+        assert_eq!(link.name, imported[0].name);
+        assert_eq!(link.tags, imported[0].tags);
+        assert_eq!(link.created_at, imported[0].created_at);
+        assert_eq!(link.read_at, imported[0].read_at);
+        assert_eq!(link.hash, imported[0].hash);
+    }
+
+    #[rstest]
+    fn test_pinboard() {
+        let json = r#"[
+{"href":"https://tokio.rs","description":"Tokio","extended":"Runtime","time":"2024-03-15T10:20:30Z","shared":"yes","toread":"no","tags":"rust async"},
+{"description":"No url"},
+{"href":"https://foo.com","description":"","extended":"","time":"2024-02-30T00:00:00Z","shared":"no","toread":"yes","tags":""}
+]"#;
+        let records = ImportFormat::Pinboard.parse(json).unwrap();
+        let link = records[0].as_ref().unwrap();
+
+        assert_eq!(3, records.len());
+        assert_eq!("Tokio", link.name);
+        assert_eq!(Some("Runtime"), link.description.as_deref());
+        assert_eq!(Some(vec!["rust".into(), "async".into()]), link.tags);
+        assert_eq!("2024-03-15 10:20:30", link.created_at);
+        assert!(link.shared && !link.toread);
+        assert_eq!(2, records[1].as_ref().unwrap_err().record);
+        assert_eq!(3, records[2].as_ref().unwrap_err().record);
+    }
+
+    #[rstest]
+    fn test_pocket() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Pocket Export</title></head>
+<body>
+<h1>Unread</h1>
+<ul>
+<li><a href="https://tokio.rs" time_added="1710498030" tags="rust,async">Tokio</a></li>
+</ul>
+<h1>Read Archive</h1>
+<ul>
+<li><a href="https://python.org" time_added="1710498030" tags="">Python</a></li>
+</ul>
+</body></html>"#;
+        let links = links(ImportFormat::Pocket.parse(html).unwrap());
+
+        assert_eq!(2, links.len());
+        assert!(links[0].toread);
+        assert!(!links[1].toread);
+        assert_eq!(Some(vec!["rust".into(), "async".into()]), links[0].tags);
+        assert_eq!("2024-03-15 10:20:30", links[1].created_at);
+    }
+
+    #[rstest]
+    #[case(ConflictPolicy::Skip, None)]
+    #[case(ConflictPolicy::Overwrite, Some(("imported", vec!["new"])))]
+    #[case(ConflictPolicy::MergeTags, Some(("existing", vec!["old", "new"])))]
+    #[case(ConflictPolicy::Newest, None)]
+    fn test_conflict_policy(
+        #[case] policy: ConflictPolicy,
+        #[case] expected: Option<(&str, Vec<&str>)>,
+    ) {
+        let existing = Link::new(
+            None,
+            "https://foo.com",
+            "existing",
+            None,
+            Some(vec!["old".into()]),
+        )
+        .set_timestamp("2024-03-15 10:00:00".into());
+        let imported = Link::new(
+            None,
+            "https://foo.com",
+            "imported",
+            None,
+            Some(vec!["new".into()]),
+        )
+        .set_timestamp("2024-01-01 10:00:00".into());
+
+        let resolved = policy.resolve(&existing, imported);
+        assert_eq!(
+            expected.map(|(name, tags)| (
+                name.to_string(),
+                Some(tags.iter().map(|t| t.to_string()).collect())
+            )),
+            resolved.map(|l| (l.name, l.tags))
+        );
+    }
+
+    #[rstest]
+    fn test_newest_policy_prefers_recently_updated() {
+        let existing = Link::new(None, "https://foo.com", "existing", None, None)
+            .set_timestamp("2024-03-15 10:00:00".into());
+        let imported = Link::new(None, "https://foo.com", "imported", None, None)
+            .set_timestamp("2024-01-01 10:00:00".into())
+            .set_updated_at(Some("2024-04-01 00:00:00".into()));
+
+        assert!(ConflictPolicy::Newest
+            .resolve(&existing, imported)
+            .is_some());
+    }
+
+    #[rstest]
+    fn test_exported_queries() {
+        let contents = "{\"links\": [{\"href\": \"https://foo.com\", \"name\": \"foo\"}], \
+                        \"queries\": [{\"id\": 1, \"name\": \"go\", \"query\": \"tags:go\"}]}";
+
+        assert_eq!(Some(ImportFormat::Json), ImportFormat::detect(contents));
+        assert_eq!(1, links(read_records(contents, None).unwrap()).len());
+
+        let queries = read_queries(contents);
+        assert_eq!(1, queries.len());
+        assert_eq!(
+            ("go", "tags:go"),
+            (queries[0].name.as_str(), queries[0].query.as_str())
+        );
+        assert!(read_queries("[{\"href\": \"https://foo.com\", \"name\": \"foo\"}]").is_empty());
+    }
+
+    #[rstest]
+    fn test_invalid_json() {
+        assert!(ImportFormat::Json.parse("{\"href\": 1}").is_err());
+    }
+}
@@ -1,21 +1,33 @@
 use crate::db::query::Query;
-use crate::db::DBError::BadVersion;
-use crate::db::DBLookupType::{Exact, Patterned};
-use crate::db::{DBLookupType, DBResult};
-use crate::utils::path;
+use crate::db::DBError::{BadCursor, BadImport, BadQuery, BadTimestamp, BadVersion};
+use crate::db::DBResult;
+use crate::utils::{matches_tag, normalize_datetime, path};
 use crate::vault::auth::Authentication;
+use crate::vault::import::{
+    self, ConflictPolicy, ImportError, ImportFormat, ImportSummary, Record,
+};
+use crate::vault::search::cursor::Cursor;
+use crate::vault::search::{self, Flag, Search};
 use crate::vault::tags::Tag;
 use crate::vault::user::User;
 use crate::vault::Vault;
 
 use clap::ArgMatches;
 use miniserde::{Deserialize, Serialize};
-use rusqlite::{params, Row};
-use rusqlite::{types::Value as SqlValue, Transaction};
+use rusqlite::{params, OptionalExtension, Row};
+use rusqlite::{types::Value as SqlValue, Connection, Transaction};
 use sha1::Sha1;
 use std::fmt;
 use std::rc::Rc;
 
+/// Links along with their tags, to be filtered and grouped by link id.
+const SELECT_LINKS: &str =
+    "SELECT l.id, href, name, description, group_concat(tag) AS tagz, is_toread, is_shared, is_favourite, datetime(l.created_at), l.deleted_at IS NOT NULL, \
+            datetime(l.updated_at), datetime(l.read_at) \
+     FROM links l \
+     LEFT JOIN links_tags lt ON l.id = lt.link_id \
+     LEFT JOIN tags t ON lt.tag_id = t.id";
+
 #[derive(Clone, Debug)]
 pub struct Version(i32);
 
@@ -43,6 +55,29 @@ impl fmt::Display for Version {
     }
 }
 
+/// Link attribute to sort by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    Created,
+    Updated,
+    Name,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+/// Order in which matching links are returned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortOrder {
+    /// Best matching links first when searching by text, most recent ones otherwise.
+    Relevance,
+    /// Links sorted by given attribute, no matter if searching by text or not.
+    By(SortKey, Direction),
+}
+
 #[derive(Serialize, Clone, Deserialize, Debug)]
 pub struct Link {
     pub id: Option<i64>,
@@ -54,13 +89,16 @@ pub struct Link {
     pub shared: bool,
     pub toread: bool,
     pub favourite: bool,
+    pub deleted: bool,
     pub created_at: String,
+    pub updated_at: Option<String>,
+    pub read_at: Option<String>,
 }
 
 impl fmt::Display for Link {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let _tags = self.tags.as_ref().map(|t| t.join(" "));
-        let s = vec![self.href.as_str()];
+        let s = [self.href.as_str()];
         write!(f, "{}", s.join("\n"))
     }
 }
@@ -74,13 +112,19 @@ impl From<&Row<'_>> for Link {
             row.get::<_, String>(3).ok().as_deref(),
             row.get::<_, String>(4)
                 .map_or(Some(Default::default()), |t| {
-                    Some(t.split(',').map(String::from).collect())
+                    let mut tags: Vec<_> = t.split(',').map(String::from).collect();
+                    tags.sort();
+                    Some(tags)
                 }),
         )
         .set_toread(row.get_unwrap::<_, bool>(5))
         .set_shared(row.get_unwrap::<_, bool>(6))
         .set_favourite(row.get_unwrap::<_, bool>(7))
         .set_timestamp(row.get_unwrap::<_, String>(8))
+        .set_deleted(row.get_unwrap::<_, bool>(9))
+        .set_updated_at(row.get_unwrap::<_, Option<String>>(10))
+        .set_read_at(row.get_unwrap::<_, Option<String>>(11))
+        .digest()
     }
 }
 
@@ -102,7 +146,10 @@ impl Link {
             shared: false,
             toread: false,
             favourite: false,
+            deleted: false,
             created_at: String::new(),
+            updated_at: None,
+            read_at: None,
         }
         .digest()
     }
@@ -118,7 +165,12 @@ impl Link {
             matches.value_of("description"),
             tags,
         )
+        .set_toread(matches.is_present("toread"))
+        .set_shared(matches.is_present("shared"))
+        .set_favourite(matches.is_present("favourite"))
     }
+    /// Calculates a hash of link's content. Hash does not depend on the order of tags,
+    /// so it stays the same no matter in what order tags were provided or fetched.
     pub fn digest(mut self) -> Self {
         let mut hasher = Sha1::new();
 
@@ -128,6 +180,8 @@ impl Link {
             hasher.update(desc.as_bytes());
         }
         if let Some(tags) = self.tags.as_ref() {
+            let mut tags = tags.clone();
+            tags.sort();
             hasher.update(tags.join(",").as_bytes());
         }
         hasher.update(self.toread.to_string().as_bytes());
@@ -144,6 +198,14 @@ impl Link {
         self.created_at = ts;
         self
     }
+    pub fn set_updated_at(mut self, ts: Option<String>) -> Self {
+        self.updated_at = ts;
+        self
+    }
+    pub fn set_read_at(mut self, ts: Option<String>) -> Self {
+        self.read_at = ts;
+        self
+    }
     pub fn set_toread(mut self, toread: bool) -> Self {
         self.toread = toread;
         self
@@ -156,21 +218,138 @@ impl Link {
         self.favourite = favourite;
         self
     }
+    pub fn set_deleted(mut self, deleted: bool) -> Self {
+        self.deleted = deleted;
+        self
+    }
+}
+
+/// Partial update of a link. Only the attributes which are set get changed.
+#[derive(Clone, Debug, Default)]
+pub struct LinkEdit {
+    pub name: Option<String>,
+    /// New description, empty one removes description altogether
+    pub description: Option<String>,
+    /// Tags to add (`tag` or `+tag`) or to remove (`-tag`)
+    pub tags: Vec<Tag>,
+    /// Flags to set (`flag` or `+flag`) or to clear (`-flag`)
+    pub flags: Vec<(Flag, bool)>,
+}
+
+impl LinkEdit {
+    pub fn set_name(mut self, name: Option<&str>) -> Self {
+        self.name = name.map(String::from);
+        self
+    }
+    pub fn set_description(mut self, description: Option<&str>) -> Self {
+        self.description = description.map(String::from);
+        self
+    }
+    pub fn set_tags(mut self, tags: Vec<Tag>) -> Self {
+        self.tags = tags
+            .into_iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        self
+    }
+    /// Sets flags given in `+flag`/`-flag` notation. Fails with the first unknown flag.
+    pub fn set_flags<S: AsRef<str>>(mut self, flags: &[S]) -> Result<Self, String> {
+        for flag in flags
+            .iter()
+            .map(|f| f.as_ref().trim())
+            .filter(|f| !f.is_empty())
+        {
+            let (name, value) = match flag.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (flag.strip_prefix('+').unwrap_or(flag), true),
+            };
+            let flag = name.parse::<Flag>().map_err(|_| name.to_string())?;
+            self.flags.push((flag, value));
+        }
+        Ok(self)
+    }
+    pub fn from_matches(matches: &ArgMatches) -> Result<LinkEdit, String> {
+        let flags: Vec<_> = matches.values_of("flags").unwrap_or_default().collect();
+
+        LinkEdit::default()
+            .set_name(matches.value_of("name"))
+            .set_description(matches.value_of("description"))
+            .set_tags(
+                matches
+                    .values_of("tags")
+                    .unwrap_or_default()
+                    .map(String::from)
+                    .collect(),
+            )
+            .set_flags(&flags)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.description.is_none()
+            && self.tags.is_empty()
+            && self.flags.is_empty()
+    }
+    /// Applies changes to the link. Changed link is considered updated now.
+    pub fn apply(&self, mut link: Link) -> Link {
+        if let Some(name) = &self.name {
+            link.name = name.clone();
+        }
+        if let Some(description) = &self.description {
+            link.description = Some(description.clone()).filter(|d| !d.trim().is_empty());
+        }
+        let (optional, required, excluded) = Vault::classify_tags(self.tags.clone());
+        let mut tags = link.tags.take().unwrap_or_default();
+        tags.retain(|t| !excluded.iter().any(|e| matches_tag(t, e)));
+        for tag in optional.into_iter().chain(required) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        link.tags = Some(tags);
+
+        for (flag, value) in &self.flags {
+            match flag {
+                Flag::ToRead => link.toread = *value,
+                Flag::Shared => link.shared = *value,
+                Flag::Favourite => link.favourite = *value,
+            }
+        }
+        link.updated_at = None;
+        link.digest()
+    }
+}
+
+/// Validates and normalizes optional timestamp of a link. Empty timestamp means no timestamp at all.
+fn timestamp(ts: &str) -> DBResult<Option<String>> {
+    if ts.trim().is_empty() {
+        Ok(None)
+    } else {
+        normalize_datetime(ts)
+            .map(Some)
+            .ok_or_else(|| BadTimestamp(ts.to_string()))
+    }
 }
 
 impl Vault {
     /// Return latest version that links have been stored with for given [`User`].
     ///
     /// If user has no links yet, returns 0 as an initial version.
-    fn get_latest_version(&self, user: &User) -> DBResult<Version> {
-        let offset = self.get_connection().query_row(
+    pub fn get_latest_version(&self, user: &User) -> DBResult<Version> {
+        self.latest_version_in(user, &self.get_connection())
+    }
+    /// Same as [`Vault::get_latest_version`], but read with given connection
+    /// (or transaction), so that version may be bumped with no race.
+    pub fn latest_version_in(&self, user: &User, conn: &Connection) -> DBResult<Version> {
+        let offset = conn.query_row(
             "SELECT ifnull(max(version), 0) FROM links WHERE user_id = ?1",
             params![user.id],
             |row| row.get::<_, i32>(0),
         )?;
         Ok(Version::new(offset))
     }
-    fn store_link(
+    /// Stores link (or updates already stored one) with given version within a transaction.
+    pub fn store_link(
         &self,
         link: Link,
         version: Version,
@@ -181,13 +360,38 @@ impl Vault {
             Version(offset) if version.is_valid() => offset,
             _ => return Err(BadVersion),
         };
+
+        // aliases are stored as their canonical tags. flags might have been changed after
+        // link was created too, let's make sure the hash reflects final state of the link.
+        let mut link = link;
+        if let Some(tags) = link.tags.take() {
+            let aliases = self.tag_aliases(user, txn)?;
+            let mut canonical: Vec<Tag> = Vec::with_capacity(tags.len());
+            for tag in tags {
+                let tag = aliases.canonical(&tag).to_string();
+                if !canonical.contains(&tag) {
+                    canonical.push(tag);
+                }
+            }
+            link.tags = Some(canonical);
+        }
+        let link = link.digest();
+
+        // link gets current time as its creation time, unless it comes with own one (eg. when
+        // imported). the same applies to update time of links which are already stored.
+        let created_at = timestamp(&link.created_at)?;
+        let updated_at = timestamp(link.updated_at.as_deref().unwrap_or_default())?;
+        let read_at = timestamp(link.read_at.as_deref().unwrap_or_default())?;
+
         txn.execute(
-            "INSERT INTO links(href, name, description, hash, is_toread, is_shared, is_favourite, user_id, version) \
-            VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9) \
+            "INSERT INTO links(href, name, description, hash, is_toread, is_shared, is_favourite, user_id, version, created_at, updated_at, read_at) \
+            VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ifnull(?10, CURRENT_TIMESTAMP), ?11, ?12) \
             ON CONFLICT(path(href), user_id) \
             DO UPDATE SET href = ?1, name = ?2, description = ?3, hash = ?4, is_toread = ?5, is_shared = ?6, is_favourite = ?7, \
-                          version = ?9, updated_at = CURRENT_TIMESTAMP",
-            params![link.href, link.name, link.description, link.hash, link.toread, link.shared, link.favourite, user.id, offset],
+                          version = ?9, created_at = ifnull(?10, created_at), updated_at = ifnull(?11, CURRENT_TIMESTAMP), \
+                          read_at = ifnull(?12, read_at), deleted_at = NULL",
+            params![link.href, link.name, link.description, link.hash, link.toread, link.shared, link.favourite, user.id, offset,
+                    created_at, updated_at, read_at],
         )?;
         let meta: (i64, String) = txn
             .query_row(
@@ -222,7 +426,10 @@ impl Vault {
     }
     pub fn add_link(&self, auth: &Option<Authentication>, link: Link) -> DBResult<Version> {
         let user = self.authenticate_user(auth)?;
-        self.add_links(auth, vec![link], self.get_latest_version(&user)?)
+
+        // single link is always stored on top of the latest version, so it never
+        // conflicts with links (or tombstones) stored before.
+        self.add_links(auth, vec![link], self.get_latest_version(&user)?.bump())
     }
     pub fn add_links(
         &self,
@@ -267,152 +474,240 @@ impl Vault {
         txn.commit()?;
         Ok(ver)
     }
-    pub fn import_links(&self, auth: &Option<Authentication>, links: Vec<Link>) -> DBResult<u32> {
+    /// Imports links read from a file. Erroneous records, both the ones which couldn't be read
+    /// and the ones which couldn't be stored, are skipped without aborting the whole import.
+    ///
+    /// Links which are already stored are resolved according to given policy and left unchanged
+    /// if the result has the same digest as stored link. Dry run reports the very same outcome
+    /// without writing anything to database.
+    pub fn import_links(
+        &self,
+        auth: &Option<Authentication>,
+        records: Vec<Record>,
+        policy: ConflictPolicy,
+        dry_run: bool,
+    ) -> DBResult<ImportSummary> {
         let user = self.authenticate_user(auth)?;
         let mut conn = self.get_connection();
         let mut ver = self.get_latest_version(&user)?.bump();
         let txn = conn.transaction().unwrap();
 
-        let mut imported: u32 = 0;
-        for link in links {
-            let (created_link, version) = self.store_link(link, ver, &user, &txn)?;
-            imported += 1;
-            ver = version;
-            println!("+ {}", created_link.href)
+        let mut summary: ImportSummary = Default::default();
+        for (i, record) in records.into_iter().enumerate() {
+            let link = match record {
+                Ok(link) => link.digest(),
+                Err(e) => {
+                    summary.skip(e);
+                    continue;
+                }
+            };
+            let existing = self.find_own_link(&link.href, &user, &txn)?;
+            let link = match &existing {
+                Some(existing) => match policy.resolve(existing, link) {
+                    Some(link) if link.hash != existing.hash => link,
+                    _ => {
+                        summary.unchanged(&existing.href);
+                        continue;
+                    }
+                },
+                None => link,
+            };
+            let href = link.href.clone();
+            match self.store_link(link, ver.clone(), &user, &txn) {
+                Ok((_, version)) => {
+                    ver = version;
+                    if existing.is_some() {
+                        summary.updated(&href);
+                    } else {
+                        summary.created(&href);
+                    }
+                }
+                Err(e) => summary.skip(ImportError::new(i + 1, &e.to_string())),
+            }
         }
-        txn.commit()?;
-        Ok(imported)
+
+        // dry run goes through all the steps of regular import (so that duplicates within
+        // imported file are reported correctly), but nothing gets eventually committed.
+
+        if dry_run {
+            txn.rollback()?;
+        } else {
+            txn.commit()?;
+        }
+        Ok(summary)
+    }
+    /// Imports links and stored queries from the contents of a file in given format
+    /// (detected if not provided). Fails with [`BadImport`] if file cannot be read at all.
+    pub fn import_file(
+        &self,
+        auth: &Option<Authentication>,
+        contents: &str,
+        format: Option<ImportFormat>,
+        policy: ConflictPolicy,
+        dry_run: bool,
+    ) -> DBResult<ImportSummary> {
+        let records = import::read_records(contents, format).map_err(BadImport)?;
+        let mut summary = self.import_links(auth, records, policy, dry_run)?;
+        let queries = import::read_queries(contents);
+        self.import_queries(auth, queries, policy, dry_run, &mut summary)?;
+        Ok(summary)
+    }
+    /// Recalculates hashes of given links, eg. after their tags have been changed directly
+    /// in database, and stores them with given version so that synced clients get the change.
+    pub fn refresh_links(&self, ids: &[i64], version: &Version, txn: &Transaction) -> DBResult<()> {
+        for id in ids {
+            let link = txn.query_row(
+                &format!("{} WHERE l.id = ?1 GROUP BY l.id", SELECT_LINKS),
+                params![id],
+                |row| Ok(Link::from(row)),
+            )?;
+            txn.execute(
+                "UPDATE links SET hash = ?1, version = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?3",
+                params![link.hash, version.offset(), id],
+            )?;
+        }
+        Ok(())
+    }
+    /// Looks for a (non-deleted) link with given url, owned by user.
+    pub fn find_own_link(
+        &self,
+        href: &str,
+        user: &User,
+        txn: &Transaction,
+    ) -> DBResult<Option<Link>> {
+        txn.query_row(
+            &format!(
+                "{} WHERE path(l.href) = path(?1) AND l.user_id = ?2 AND l.deleted_at IS NULL GROUP BY l.id",
+                SELECT_LINKS
+            ),
+            params![href, user.id],
+            |row| Ok(Link::from(row)),
+        )
+        .optional()
+        .map_err(Into::into)
     }
     pub fn find_links(
         &self,
         auth: &Option<Authentication>,
-        pattern: Link,
-        lookup_type: DBLookupType,
+        search: &Search,
         version: Version,
         limit: Option<u16>,
     ) -> DBResult<(Vec<Link>, Version)> {
         let user = self.authenticate_user(auth)?;
-        let mut query = Query::new_with_initial(
-            "SELECT l.id, href, name, description, group_concat(tag) AS tagz, is_toread, is_shared, is_favourite, datetime(l.created_at) \
-             FROM links l \
-             LEFT JOIN links_tags lt ON l.id = lt.link_id \
-             LEFT JOIN tags t ON lt.tag_id = t.id WHERE",
-        );
-
-        let tags = pattern.tags.to_owned().unwrap_or_default();
-        let path = path(pattern.href.as_str());
-        let name = Query::patternize(&pattern.name);
+        let (mut query, _) = self.links_query(&user, search, &version, None)?;
         let limit = limit.unwrap_or(0);
-        let offset = version.offset();
+
+        // Finally the limit. It's not the best idea to return all the links if no constraints
+        // were provided. Let's limit result up to 10 links by default.
+
+        if limit > 0 {
+            query.concat_with_param("LIMIT :limit", (":limit", &limit));
+        }
+        Ok((
+            query.fetch(self.get_connection())?,
+            self.get_latest_version(&user)?,
+        ))
+    }
+    /// Finds a page of links matching the search, following given cursor (or the very first
+    /// page if no cursor was provided). Returns cursor of the next page, if there is one.
+    pub fn find_page(
+        &self,
+        auth: &Option<Authentication>,
+        search: &Search,
+        version: Version,
+        limit: u16,
+        cursor: Option<Cursor>,
+    ) -> DBResult<(Vec<Link>, Version, Option<Cursor>)> {
+        let user = self.authenticate_user(auth)?;
+        let cursor = match cursor {
+            Some(cursor) => cursor,
+            None => Cursor::new(self.get_connection().query_row(
+                "SELECT ifnull(max(id), 0) FROM links WHERE user_id = ?1 OR is_shared",
+                params![user.id],
+                |row| row.get(0),
+            )?),
+        };
+        let (mut query, is_ranked) = self.links_query(&user, search, &version, Some(&cursor))?;
+
+        // one more link is fetched to find out if there is a next page at all.
+
+        if limit > 0 {
+            query.concat_with_param("LIMIT :limit + 1", (":limit", &limit));
+        }
+        let mut links: Vec<Link> = query.fetch(self.get_connection())?;
+        let next = if limit > 0 && links.len() > limit as usize {
+            links.truncate(limit as usize);
+            let last = links.last().and_then(|l| l.id).unwrap_or_default();
+            let keys = self.sort_values(search, is_ranked, last)?;
+            Some(Cursor::new(cursor.snapshot).set_keys(keys))
+        } else {
+            None
+        };
+        Ok((links, self.get_latest_version(&user)?, next))
+    }
+    /// Builds query for links matching the search, sorted but not limited yet. Returns the
+    /// query and information whether links are ranked by relevance.
+    fn links_query<'q>(
+        &self,
+        user: &User,
+        search: &Search,
+        version: &Version,
+        cursor: Option<&Cursor>,
+    ) -> DBResult<(Query<'q>, bool)> {
+        let mut query = Query::new_with_initial(SELECT_LINKS);
+
+        // Textual terms are looked up in full-text index. Links are ranked by how well they
+        // match these terms - name is weighted the most, then description and url.
+
+        let is_ranked = search.compile_ranking(&mut query);
 
         // Apply versioning - return only these records which have version greater or equal
         // to provided one. version = -1 means that all records should be returned (except
         // from deleted ones for performance reason).
 
         if version.is_valid() {
-            query.concat_with_param("version >= :version AND", (":version", &offset));
+            let offset = query.bind(version.offset());
+            query.concat_owned(format!("WHERE version >= {} AND", offset));
         } else {
-            query.concat("deleted_at IS NULL AND");
+            query.concat("WHERE deleted_at IS NULL AND");
         }
+        let id = query.bind(user.id);
+        query.concat_owned(format!("(l.user_id = {} OR l.is_shared)", id));
 
-        // Searching by name and description is equivalent. Also, when href was not not explicitly
-        // provided it's equivalent to name. This is to easily find a link by either a name/description
-        // or some part of url.
-
-        if !name.is_empty() {
-            if path.is_empty() {
-                query.concat_with_param(
-                    "(name LIKE :name OR href LIKE :name OR description LIKE :name) AND",
-                    (":name", &name),
-                );
-            } else {
-                query.concat_with_param(
-                    "(name LIKE :name OR description LIKE :name) AND",
-                    (":name", &name),
-                );
-            }
-        }
-        let href = match lookup_type {
-            Exact => path,
-            Patterned => Query::patternize(&path),
-        };
-        if !href.is_empty() {
-            query.concat_with_param("path(href) LIKE :href AND", (":href", &href));
-        }
+        // Pages start right after the cursor, which is compared with the very same keys
+        // links are sorted by.
 
-        // 2 special flags to be handled here: toread and favourite:
-        if pattern.toread {
-            query.concat("l.is_toread = TRUE AND");
+        if let Some(cursor) = cursor {
+            let condition = cursor
+                .compile(&search.sort_keys(is_ranked), &mut query)
+                .ok_or(BadCursor)?;
+            query.concat_owned(format!("AND {}", condition));
         }
-        if pattern.favourite {
-            query.concat("l.is_favourite = TRUE AND");
-        }
-        query.concat_with_param(
-            "(l.user_id = :id OR l.is_shared) GROUP BY l.id",
-            (":id", &user.id),
-        );
+        query.concat("GROUP BY l.id");
 
-        // Tags are classified as: optional, +required and -excluded.
-        //
-        // Each classification follows different rule to decide whether to include or exclude
-        // link from results. And so, for any given link with tags attached, to add link to
-        // final results:
-        //
-        // - at least one of optional tags needs to be added to the link
-        // - all of required tags need to be added to the link
-        // - all of excluded tags need to be missing
-        //
-        // Rules can be combined together when tags of different classification are used in a query,
-        // eg. "tags:rust,programming,-hyper,+server" means that all links tagged either with "rust"
-        // or "programming", having no "hyper" tag and having "server" tag should be returned.
-
-        let (optional, required, excluded) = Vault::classify_tags(tags);
-
-        let has_optional = !optional.is_empty();
-        let has_required = !required.is_empty();
-        let has_excluded = !excluded.is_empty();
-
-        let excluded = excluded.join(",");
-        let required = required.join(",");
-
-        let optional_ptr = Rc::<Vec<_>>::new(optional.into_iter().map(SqlValue::from).collect());
-        if has_optional || has_required || has_excluded {
-            query.concat("HAVING");
-
-            if has_optional {
-                query.concat_with_param(
-                    "l.id IN (\
-                     SELECT link_id FROM links_tags lt2 \
-                     JOIN tags t2 ON lt2.tag_id = t2.id AND t2.tag IN rarray(:tags)) AND",
-                    (":tags", &optional_ptr),
-                );
-            }
-            if has_required {
-                query.concat_with_param(
-                    "LENGTH(tagz) > 0 AND every(tagz, :reqs) AND",
-                    (":reqs", &required),
-                );
-            }
-            if has_excluded {
-                query.concat_with_param(
-                    "LENGTH(tagz) > 0 AND some(tagz, :excls) = FALSE AND",
-                    (":excls", &excluded),
-                );
-            }
-            query.concat("1=1");
-        }
-        query.concat("ORDER BY l.created_at DESC, l.is_favourite DESC");
+        // Query itself is applied on grouped links, as tags are matched against
+        // concatenated list of all the tags attached to link.
 
-        // Finally the limit. It's not the best idea to return all the links if no constraints
-        // were provided. Let's limit result up to 10 links by default.
+        search.compile_filter(&mut query, &self.tag_aliases(user, &self.get_connection())?);
+        search.compile_order(&mut query, is_ranked);
 
-        if limit > 0 {
-            query.concat_with_param("LIMIT :limit", (":limit", &limit));
-        }
-        Ok((
-            query.fetch(self.get_connection())?,
-            self.get_latest_version(&user)?,
-        ))
+        Ok((query, is_ranked))
+    }
+    /// Values of sort keys of given link.
+    fn sort_values(&self, search: &Search, is_ranked: bool, id: i64) -> DBResult<Vec<SqlValue>> {
+        let keys = search.sort_keys(is_ranked);
+        let columns: Vec<_> = keys.iter().map(|(column, _)| *column).collect();
+        let mut query = Query::new();
+        query.concat_owned(format!("SELECT {} FROM links l", columns.join(", ")));
+        search.compile_ranking(&mut query);
+        query.concat_with_param("WHERE l.id = :id", (":id", &id));
+
+        let values = query.fetch_as(self.get_connection(), |row| {
+            (0..keys.len())
+                .map(|i| row.get_unwrap::<_, SqlValue>(i))
+                .collect::<Vec<_>>()
+        })?;
+        Ok(values.into_iter().next().unwrap_or_default())
     }
     pub fn get_href(&self, auth: &Option<Authentication>, link_id: i64) -> DBResult<String> {
         let user = self.authenticate_user(auth)?;
@@ -423,98 +718,108 @@ impl Vault {
         )?;
         Ok(href)
     }
+    /// Looks for a link with given url, visible to user (either owned or shared).
+    #[cfg(test)]
     pub fn get_link(&self, auth: &Option<Authentication>, href: &str) -> DBResult<Option<Link>> {
-        let pattern = Link::new(None, &href, "", None, None);
-        self.find_links(
-            auth,
-            pattern,
-            DBLookupType::Exact,
-            Version::unknown(),
-            Some(1),
-        )
-        .map(|(links, _)| links.first().cloned())
+        self.find_links(auth, &Search::url(href), Version::unknown(), Some(1))
+            .map(|(links, _)| links.first().cloned())
     }
+    /// Marks a link as deleted rather than removing it physically from database.
+    ///
+    /// Deleted link (a tombstone) gets a new version assigned, so that clients which
+    /// synchronize incrementally learn about deletion when asking for links at given version.
+    /// Tombstones are eventually removed by [`Vault::purge_links`].
+    /// Only user's own links can be deleted, shared links of other users are left intact.
     pub fn del_link(&self, auth: &Option<Authentication>, href: &str) -> DBResult<Option<Link>> {
-        match self.get_link(auth, &href) {
-            Ok(Some(link)) => {
-                self.get_connection()
-                    .execute("DELETE FROM links WHERE id = ?", params![link.id])?;
-                Ok(Some(link))
-            }
-            Ok(None) => Ok(None),
-            Err(e) => Err(e),
-        }
+        let user = self.authenticate_user(auth)?;
+        let mut conn = self.get_connection();
+        let txn = conn.transaction().unwrap();
+
+        let link = match self.find_own_link(href, &user, &txn)? {
+            Some(link) => link,
+            None => return Ok(None),
+        };
+        let version = self.latest_version_in(&user, &txn)?.bump();
+        txn.execute(
+            "UPDATE links SET deleted_at = CURRENT_TIMESTAMP, version = ?1 \
+            WHERE id = ?2 AND user_id = ?3",
+            params![version.offset(), link.id, user.id],
+        )?;
+        txn.commit()?;
+        Ok(Some(link.set_deleted(true)))
+    }
+    /// Partially updates user's own link. Link gets a new version, its creation time stays intact.
+    pub fn edit_link(
+        &self,
+        auth: &Option<Authentication>,
+        href: &str,
+        edit: &LinkEdit,
+    ) -> DBResult<Option<Link>> {
+        let user = self.authenticate_user(auth)?;
+        let mut conn = self.get_connection();
+        let txn = conn.transaction().unwrap();
+
+        let link = match self.find_own_link(href, &user, &txn)? {
+            Some(link) => link,
+            None => return Ok(None),
+        };
+        let version = self.latest_version_in(&user, &txn)?.bump();
+        self.store_link(edit.apply(link), version, &user, &txn)?;
+
+        let link = self.find_own_link(href, &user, &txn)?;
+        txn.commit()?;
+        Ok(link)
+    }
+    /// Physically removes links which have been deleted more than `days` ago.
+    ///
+    /// Returns number of purged links.
+    pub fn purge_links(&self, auth: &Option<Authentication>, days: u32) -> DBResult<usize> {
+        let user = self.authenticate_user(auth)?;
+        let purged = self.get_connection().execute(
+            "DELETE FROM links WHERE user_id = ?1 AND deleted_at IS NOT NULL \
+            AND deleted_at <= datetime('now', ?2)",
+            params![user.id, format!("-{} days", days)],
+        )?;
+        Ok(purged)
     }
+    /// Marks user's own link as read.
     pub fn read_link(&self, auth: &Option<Authentication>, href: &str) -> DBResult<Option<Link>> {
-        match self.get_link(auth, &href) {
-            Ok(Some(link)) => {
-                self.get_connection().execute(
-                    "UPDATE links SET is_toread = FALSE, read_at = CURRENT_TIMESTAMP WHERE id = ?",
-                    params![link.id],
-                )?;
-                Ok(Some(link))
-            }
-            Ok(None) => Ok(None),
-            Err(e) => Err(e),
-        }
+        let user = self.authenticate_user(auth)?;
+        let mut conn = self.get_connection();
+        let txn = conn.transaction().unwrap();
+
+        let link = match self.find_own_link(href, &user, &txn)? {
+            Some(link) => link,
+            None => return Ok(None),
+        };
+        txn.execute(
+            "UPDATE links SET is_toread = FALSE, read_at = CURRENT_TIMESTAMP \
+            WHERE id = ?1 AND user_id = ?2",
+            params![link.id, user.id],
+        )?;
+        txn.commit()?;
+        Ok(Some(link))
     }
-    pub fn find_matching_links(
+    pub fn query_links<S: AsRef<str>>(
         &self,
         auth: &Option<Authentication>,
-        pattern: Link,
+        query: S,
         version: Version,
         limit: Option<u16>,
     ) -> DBResult<(Vec<Link>, Version)> {
-        self.find_links(auth, pattern, DBLookupType::Patterned, version, limit)
+        let search = search::parse(query.as_ref()).map_err(BadQuery)?;
+        self.find_links(auth, &search, version, limit)
     }
-    pub fn query_links<S: AsRef<str>>(
+    pub fn query_page<S: AsRef<str>>(
         &self,
         auth: &Option<Authentication>,
         query: S,
         version: Version,
-        limit: Option<u16>,
-    ) -> DBResult<(Vec<Link>, Version)> {
-        let mut href = "";
-        let mut desc = "";
-        let mut name: Vec<&str> = Vec::new();
-        let mut tags: Vec<Tag> = Vec::new();
-        let mut toread = false;
-        let mut shared = false;
-        let mut favourite = false;
-
-        for chunk in query.as_ref().split_whitespace() {
-            let ch: Vec<_> = chunk.split(':').collect();
-            if ch.len() == 2 {
-                match ch[0] {
-                    "tags" => {
-                        let more_tags = ch[1].split(',').map(String::from).collect::<Vec<String>>();
-                        tags.extend(more_tags);
-                    }
-                    "flags" => {
-                        toread = ch[1].contains("toread");
-                        shared = ch[1].contains("shared");
-                        favourite = ch[1].contains("fav");
-                    }
-                    "href" => href = ch[1],
-                    "desc" => desc = ch[1],
-                    _ => name.push(chunk),
-                }
-            } else {
-                name.push(chunk);
-            }
-        }
-        let pattern = Link::new(
-            None,
-            href.trim(),
-            name.join("%").trim(),
-            Some(desc.trim()),
-            Some(tags),
-        )
-        .set_toread(toread)
-        .set_shared(shared)
-        .set_favourite(favourite);
-
-        self.find_matching_links(auth, pattern, version, limit)
+        limit: u16,
+        cursor: Option<Cursor>,
+    ) -> DBResult<(Vec<Link>, Version, Option<Cursor>)> {
+        let search = search::parse(query.as_ref()).map_err(BadQuery)?;
+        self.find_page(auth, &search, version, limit, cursor)
     }
 }
 
@@ -523,10 +828,10 @@ mod test_links {
     #![allow(unused_must_use)]
 
     use super::*;
-    use crate::vault::test_db::{auth, vault};
+    use crate::vault::test_db::{auth, names, other, vault};
     use rstest::*;
 
-    const QUERY_EMPTY: &'static str = "";
+    const QUERY_EMPTY: &str = "";
 
     #[rstest]
     fn test_initial_query_links(vault: &Vault, auth: Option<Authentication>) {
@@ -535,7 +840,7 @@ mod test_links {
             .unwrap();
 
         assert_eq!(0, version.offset());
-        assert_eq!(true, links.is_empty());
+        assert!(links.is_empty());
     }
 
     #[rstest]
@@ -554,7 +859,256 @@ mod test_links {
 
     #[rstest]
     fn test_query_for_links_at_specific_version(vault: &Vault, auth: Option<Authentication>) {
-        unimplemented!()
+        vault.add_link(&auth, Link::new(None, "http://foo.io", "foo", None, None));
+        vault.add_link(&auth, Link::new(None, "http://moo.io", "moo", None, None));
+        vault.add_link(&auth, Link::new(None, "http://zoo.io", "zoo", None, None));
+
+        let (links, version) = vault
+            .query_links(&auth, QUERY_EMPTY, Version::new(2), None)
+            .unwrap();
+
+        let mut names: Vec<_> = links.iter().map(|l| l.name.as_str()).collect();
+        names.sort_unstable();
+
+        assert_eq!(3, version.offset());
+        assert_eq!(vec!["moo", "zoo"], names);
+    }
+
+    #[rstest]
+    fn test_deleted_link_returned_as_tombstone(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(&auth, Link::new(None, "http://foo.io", "foo", None, None));
+        vault.add_link(&auth, Link::new(None, "http://moo.io", "moo", None, None));
+        vault.del_link(&auth, "http://foo.io");
+
+        let (links, version) = vault
+            .query_links(&auth, QUERY_EMPTY, Version::unknown(), None)
+            .unwrap();
+
+        assert_eq!(3, version.offset());
+        assert_eq!(1, links.len());
+        assert_eq!("moo", links.first().unwrap().name);
+
+        let (links, _) = vault
+            .query_links(&auth, QUERY_EMPTY, Version::new(3), None)
+            .unwrap();
+
+        assert_eq!(1, links.len());
+        assert_eq!("foo", links.first().unwrap().name);
+        assert!(links.first().unwrap().deleted);
+    }
+
+    #[rstest]
+    fn test_shared_link_deleted_by_owner_only(
+        vault: &Vault,
+        auth: Option<Authentication>,
+        other: Option<Authentication>,
+    ) {
+        let link = Link::new(None, "http://shared.io", "shared", None, None).set_shared(true);
+        let version = vault.add_link(&auth, link.set_toread(true)).unwrap();
+
+        assert!(vault
+            .get_link(&other, "http://shared.io")
+            .unwrap()
+            .is_some());
+        assert!(vault
+            .del_link(&other, "http://shared.io")
+            .unwrap()
+            .is_none());
+        assert!(vault
+            .read_link(&other, "http://shared.io")
+            .unwrap()
+            .is_none());
+
+        let (links, latest) = vault
+            .query_links(&auth, QUERY_EMPTY, Version::unknown(), None)
+            .unwrap();
+        assert_eq!(version.offset(), latest.offset());
+        assert!(!links[0].deleted && links[0].toread);
+
+        assert!(vault.del_link(&auth, "http://shared.io").unwrap().is_some());
+        let (links, _) = vault
+            .query_links(&auth, QUERY_EMPTY, version, None)
+            .unwrap();
+        assert!(links[0].deleted);
+    }
+
+    #[rstest]
+    fn test_readded_link_is_no_longer_deleted(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(&auth, Link::new(None, "http://foo.io", "foo", None, None));
+        vault.del_link(&auth, "http://foo.io");
+        vault.add_link(&auth, Link::new(None, "http://foo.io", "foo", None, None));
+
+        let (links, version) = vault
+            .query_links(&auth, QUERY_EMPTY, Version::unknown(), None)
+            .unwrap();
+
+        assert_eq!(3, version.offset());
+        assert_eq!(1, links.len());
+        assert!(!links.first().unwrap().deleted);
+    }
+
+    #[rstest]
+    fn test_purge_removes_old_tombstones(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(&auth, Link::new(None, "http://foo.io", "foo", None, None));
+        vault.del_link(&auth, "http://foo.io");
+
+        assert_eq!(0, vault.purge_links(&auth, 1).unwrap());
+        assert_eq!(1, vault.purge_links(&auth, 0).unwrap());
+
+        let (links, _) = vault
+            .query_links(&auth, QUERY_EMPTY, Version::new(0), None)
+            .unwrap();
+
+        assert!(links.is_empty());
+    }
+
+    #[rstest]
+    fn test_import_summary(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(&auth, Link::new(None, "http://foo.io", "foo", None, None));
+
+        let summary = vault
+            .import_links(
+                &auth,
+                vec![
+                    Ok(Link::new(None, "http://foo.io", "updated foo", None, None)),
+                    Err(ImportError::new(2, "missing url")),
+                    Ok(Link::new(None, "http://bar.io", "bar", None, None)),
+                    Ok(Link::new(None, "http://bar.io", "bar", None, None)),
+                ],
+                ConflictPolicy::Overwrite,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(
+            (1, 1, 1, 1),
+            (
+                summary.created,
+                summary.updated,
+                summary.unchanged,
+                summary.skipped
+            )
+        );
+        assert_eq!(vec![ImportError::new(2, "missing url")], summary.errors);
+    }
+
+    #[rstest]
+    fn test_add_link_with_timestamps(vault: &Vault, auth: Option<Authentication>) {
+        let link = Link::new(None, "http://old.io", "old", None, None)
+            .set_timestamp("2001-02-03T04:05:06Z".to_string())
+            .set_read_at(Some("2001-02-04".to_string()));
+        vault.add_link(&auth, link);
+
+        let link = vault.get_link(&auth, "http://old.io").unwrap().unwrap();
+        assert_eq!("2001-02-03 04:05:06", link.created_at);
+        assert_eq!(None, link.updated_at);
+        assert_eq!(Some("2001-02-04 00:00:00".to_string()), link.read_at);
+
+        // creation time stays intact when link gets updated with no timestamps provided
+        vault.add_link(&auth, Link::new(None, "http://old.io", "new", None, None));
+
+        let link = vault.get_link(&auth, "http://old.io").unwrap().unwrap();
+        assert_eq!("2001-02-03 04:05:06", link.created_at);
+        assert!(link.updated_at.is_some());
+
+        for invalid in &[
+            "2001-02-30",
+            "2001-13-01",
+            "2024-01-9223372036854775807",
+            "9223372036854775807-01-01",
+            "2001-01-01 10:00:9223372036854775807",
+        ] {
+            let link = Link::new(None, "http://invalid.io", "invalid", None, None)
+                .set_timestamp(invalid.to_string());
+            assert!(matches!(
+                vault.add_link(&auth, link),
+                Err(BadTimestamp(ts)) if ts == *invalid
+            ));
+        }
+    }
+
+    #[rstest]
+    fn test_edit_link(vault: &Vault, auth: Option<Authentication>) {
+        let link = Link::new(
+            None,
+            "http://edit.io",
+            "edit",
+            Some("description"),
+            Some(vec!["rust".into(), "go".into()]),
+        )
+        .set_timestamp("2001-02-03 04:05:06".to_string())
+        .set_toread(true);
+        let version = vault.add_link(&auth, link).unwrap();
+
+        let edit = LinkEdit::default()
+            .set_name(Some("edited"))
+            .set_description(Some(""))
+            .set_tags(vec!["+async".into(), "-go".into()])
+            .set_flags(&["-toread", "fav"])
+            .unwrap();
+        let edited = vault
+            .edit_link(&auth, "http://edit.io", &edit)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!("edited", edited.name);
+        assert_eq!(None, edited.description);
+        assert_eq!(Some(vec!["async".into(), "rust".into()]), edited.tags);
+        assert!(!edited.toread && edited.favourite);
+        assert_eq!("2001-02-03 04:05:06", edited.created_at);
+
+        let (links, _) = vault
+            .query_links(&auth, QUERY_EMPTY, version.bump(), None)
+            .unwrap();
+        assert_eq!(1, links.len());
+        assert_eq!(edited.hash, links[0].hash);
+
+        assert!(vault
+            .edit_link(&auth, "http://nope.io", &edit)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            Err("later".to_string()),
+            LinkEdit::default().set_flags(&["later"]).map(|_| ())
+        );
+    }
+
+    #[rstest]
+    fn test_import_dry_run(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(&auth, Link::new(None, "http://foo.io", "foo", None, None));
+
+        let records = || {
+            vec![
+                Ok(Link::new(None, "http://foo.io", "updated foo", None, None)),
+                Ok(Link::new(None, "http://bar.io", "bar", None, None)),
+            ]
+        };
+        let dry = vault
+            .import_links(&auth, records(), ConflictPolicy::Overwrite, true)
+            .unwrap();
+        let (links, _) = vault
+            .query_links(&auth, QUERY_EMPTY, Version::unknown(), None)
+            .unwrap();
+
+        assert_eq!(1, links.len());
+        assert_eq!("foo", links[0].name);
+        assert_eq!((1, 1), (dry.created, dry.updated));
+
+        let skipped = vault
+            .import_links(&auth, records(), ConflictPolicy::Skip, false)
+            .unwrap();
+        assert_eq!(
+            (1, 0, 1),
+            (skipped.created, skipped.updated, skipped.unchanged)
+        );
+        assert_eq!(
+            "foo",
+            vault
+                .get_link(&auth, "http://foo.io")
+                .unwrap()
+                .unwrap()
+                .name
+        );
     }
 
     #[rstest]
@@ -597,13 +1151,281 @@ mod test_links {
             .query_links(&auth, QUERY_EMPTY, Version::unknown(), None)
             .unwrap();
 
-        let rejected: Vec<_> = links
-            .iter()
-            .filter(|l| l.name == "foo modified")
-            .collect();
+        let rejected: Vec<_> = links.iter().filter(|l| l.name == "foo modified").collect();
 
-        assert_eq!(true, rejected.is_empty());
+        assert!(rejected.is_empty());
         assert_eq!(2, version.offset());
         assert_eq!(3, links.len());
     }
+
+    #[rstest]
+    fn test_text_search_ignores_words_order(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(
+            &auth,
+            Link::new(
+                None,
+                "http://tokio.rs",
+                "Asynchronous Rust runtime",
+                None,
+                None,
+            ),
+        );
+        vault.add_link(&auth, Link::new(None, "http://go.dev", "Go", None, None));
+
+        let (links, _) = vault
+            .query_links(&auth, "runtime asynchronous", Version::unknown(), None)
+            .unwrap();
+
+        assert_eq!(1, links.len());
+        assert_eq!("http://tokio.rs", links.first().unwrap().href);
+    }
+
+    #[rstest]
+    fn test_text_search_ranks_by_relevance(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(
+            &auth,
+            Link::new(None, "http://rust-lang.org", "Rust", None, None),
+        );
+        vault.add_link(
+            &auth,
+            Link::new(None, "http://foo.io", "Foo", Some("written in rust"), None),
+        );
+
+        let (links, _) = vault
+            .query_links(&auth, "rust", Version::unknown(), None)
+            .unwrap();
+        let names: Vec<_> = links.iter().map(|l| l.name.as_str()).collect();
+
+        assert_eq!(vec!["Rust", "Foo"], names);
+
+        let (links, _) = vault
+            .query_links(&auth, "rust sort:created", Version::unknown(), None)
+            .unwrap();
+
+        assert_eq!(2, links.len());
+    }
+
+    #[rstest]
+    fn test_text_search_respects_visibility(
+        vault: &Vault,
+        auth: Option<Authentication>,
+        other: Option<Authentication>,
+    ) {
+        vault.add_link(
+            &other,
+            Link::new(None, "http://secret.io", "Secret stuff", None, None),
+        );
+        vault.add_link(
+            &auth,
+            Link::new(None, "http://my.io", "My stuff", None, None),
+        );
+
+        let (links, _) = vault
+            .query_links(&auth, "stuff", Version::unknown(), None)
+            .unwrap();
+
+        assert_eq!(1, links.len());
+        assert_eq!("http://my.io", links.first().unwrap().href);
+    }
+
+    #[rstest]
+    fn test_query_with_boolean_operators(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(
+            &auth,
+            Link::new(
+                None,
+                "http://tokio.rs",
+                "Tokio",
+                None,
+                Some(vec!["rust".into()]),
+            ),
+        );
+        vault.add_link(
+            &auth,
+            Link::new(None, "http://go.dev", "Go", None, Some(vec!["go".into()])),
+        );
+        vault.add_link(
+            &auth,
+            Link::new(
+                None,
+                "http://hyper.rs",
+                "Hyper",
+                None,
+                Some(vec!["rust".into(), "http".into()]),
+            ),
+        );
+
+        assert_eq!(vec!["Go", "Tokio"], names(vault, &auth, "tokio OR tags:go"));
+        assert_eq!(
+            vec!["Tokio"],
+            names(vault, &auth, "tags:rust NOT tags:http")
+        );
+        assert_eq!(vec!["Hyper"], names(vault, &auth, "tags:rust tags:http"));
+        assert_eq!(
+            vec!["Go", "Hyper"],
+            names(vault, &auth, "NOT (tokio OR href:https://foo.io)")
+        );
+        assert_eq!(vec!["Hyper"], names(vault, &auth, "href:hyper.rs"));
+    }
+
+    #[rstest]
+    fn test_query_with_hierarchical_tags(vault: &Vault, auth: Option<Authentication>) {
+        let tagged = [
+            ("Rust", vec!["lang/rust", "work/infra"]),
+            ("Go", vec!["lang/go"]),
+            ("Lang", vec!["lang"]),
+            ("K8s", vec!["work/infra/k8s"]),
+            ("Language", vec!["language"]),
+        ];
+        for (name, tags) in tagged.iter() {
+            vault.add_link(
+                &auth,
+                Link::new(
+                    None,
+                    &format!("http://{}.io", name),
+                    name,
+                    None,
+                    Some(tags.iter().map(|t| t.to_string()).collect()),
+                ),
+            );
+        }
+
+        assert_eq!(
+            vec!["Go", "Lang", "Rust"],
+            names(vault, &auth, "tags:lang/*")
+        );
+        assert_eq!(vec!["Lang"], names(vault, &auth, "tags:lang"));
+        assert_eq!(
+            vec!["K8s", "Rust"],
+            names(vault, &auth, "tags:+work/infra/*")
+        );
+        assert_eq!(
+            vec!["Go", "Lang"],
+            names(vault, &auth, "tags:lang/*,-work/*")
+        );
+        assert_eq!(vec!["Rust"], names(vault, &auth, "tags:+lang/*,+work/*"));
+    }
+
+    #[rstest]
+    fn test_paginated_links(vault: &Vault, auth: Option<Authentication>) {
+        for (i, name) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            vault.add_link(
+                &auth,
+                Link::new(None, &format!("http://{}.page", name), name, None, None)
+                    .set_timestamp(format!("2024-01-0{} 10:00:00", i + 1)),
+            );
+        }
+        let pages = |query: &str, inserted: Option<&str>| {
+            let mut pages = Vec::new();
+            let mut cursor = None;
+            loop {
+                let (links, _, next) = vault
+                    .query_page(&auth, query, Version::unknown(), 2, cursor)
+                    .unwrap();
+                pages.push(links.into_iter().map(|l| l.name).collect::<Vec<_>>());
+
+                // links inserted concurrently don't shift pages
+                if let Some(inserted) = inserted {
+                    vault.add_link(
+                        &auth,
+                        Link::new(
+                            None,
+                            &format!("http://{}.page", inserted),
+                            inserted,
+                            None,
+                            None,
+                        ),
+                    );
+                }
+                match next {
+                    Some(next) => cursor = Some(Cursor::decode(&next.encode()).unwrap()),
+                    None => return pages,
+                }
+            }
+        };
+
+        assert_eq!(
+            vec![vec!["e", "d"], vec!["c", "b"], vec!["a"]],
+            pages("href:page", Some("x"))
+        );
+        assert_eq!(
+            vec![vec!["a", "b"], vec!["c", "d"], vec!["e"]],
+            pages("href:page sort:name NOT name:x", Some("y"))
+        );
+        assert_eq!(
+            vec![vec!["e", "d"], vec!["c", "b"], vec!["a"]],
+            pages("page sort:-created NOT name:x NOT name:y", Some("page"))
+        );
+    }
+
+    #[rstest]
+    fn test_paginated_ranked_links(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(
+            &auth,
+            Link::new(None, "http://one.io", "ranked", None, None),
+        );
+        vault.add_link(
+            &auth,
+            Link::new(None, "http://three.io", "three", Some("ranked"), None),
+        );
+        vault.add_link(
+            &auth,
+            Link::new(None, "http://ranked.io", "two", None, None),
+        );
+
+        let mut pages = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (links, _, next) = vault
+                .query_page(&auth, "ranked", Version::unknown(), 1, cursor)
+                .unwrap();
+            pages.extend(links.into_iter().map(|l| l.name));
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(vec!["ranked", "three", "two"], pages);
+    }
+
+    #[rstest]
+    fn test_paginated_links_with_foreign_cursor(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(&auth, Link::new(None, "http://foo.io", "foo", None, None));
+        vault.add_link(&auth, Link::new(None, "http://bar.io", "bar", None, None));
+
+        let (_, _, next) = vault
+            .query_page(&auth, "sort:name", Version::unknown(), 1, None)
+            .unwrap();
+        let result = vault.query_page(&auth, "", Version::unknown(), 1, next);
+
+        assert!(matches!(result, Err(BadCursor)));
+    }
+
+    #[rstest]
+    fn test_query_with_syntax_error(vault: &Vault, auth: Option<Authentication>) {
+        let result = vault.query_links(&auth, "rust tags:", Version::unknown(), None);
+
+        assert!(matches!(result, Err(BadQuery(e)) if e.position == 5));
+    }
+
+    #[rstest]
+    fn test_query_with_dates(vault: &Vault, auth: Option<Authentication>) {
+        vault.add_link(&auth, Link::new(None, "http://foo.io", "foo", None, None));
+        vault.add_link(&auth, Link::new(None, "http://moo.io", "moo", None, None));
+        vault.read_link(&auth, "http://moo.io");
+
+        let names = |query: &str| {
+            let (links, _) = vault
+                .query_links(&auth, query, Version::unknown(), None)
+                .unwrap();
+            links.into_iter().map(|l| l.name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["foo", "moo"], names("created:>2000 sort:name"));
+        assert_eq!(vec!["moo", "foo"], names("updated:<7d sort:-name"));
+        assert!(names("created:<=2000-12").is_empty());
+        assert!(names("created:>1d").is_empty());
+        assert_eq!(vec!["foo"], names("read:never"));
+        assert_eq!(vec!["moo"], names("NOT read:never"));
+    }
 }
@@ -0,0 +1,224 @@
+use crate::db::query::Query;
+use crate::db::DBError::{BadPassword, LockedOut, UnknownUser};
+use crate::db::DBResult;
+use crate::utils::{datetime, now, unix_timestamp};
+use crate::vault::auth::Authentication;
+use crate::vault::user::User;
+use crate::vault::Vault;
+
+use miniserde::Serialize;
+use rusqlite::{params, OptionalExtension, Row};
+use std::cmp::min;
+
+/// Failed attempts allowed before each next one gets delayed.
+const FREE_ATTEMPTS: u32 = 3;
+
+/// Failed attempts after which logging in gets locked out.
+const MAX_ATTEMPTS: u32 = 10;
+
+/// How long lockout lasts, in seconds. Also the longest delay between attempts.
+const LOCKOUT_SECS: i64 = 15 * 60;
+
+/// How long failures are remembered since the last one, in seconds.
+const FORGET_SECS: i64 = 24 * 60 * 60;
+
+/// Failed login attempts of either a login (`login` kind) or a client address (`ip` kind).
+#[derive(Serialize, Clone, Debug)]
+pub struct Lockout {
+    pub kind: String,
+    pub subject: String,
+    pub failures: u32,
+    pub failed_at: String,
+    pub locked_until: Option<String>,
+}
+
+impl From<&Row<'_>> for Lockout {
+    fn from(row: &Row) -> Self {
+        Lockout {
+            kind: row.get_unwrap(0),
+            subject: row.get_unwrap(1),
+            failures: row.get_unwrap(2),
+            failed_at: row.get_unwrap(3),
+            locked_until: row.get_unwrap(4),
+        }
+    }
+}
+
+/// Number of seconds next attempt is delayed by after given number of failures.
+/// Delay doubles with each failure, up to a lockout.
+fn backoff(failures: u32) -> i64 {
+    if failures >= MAX_ATTEMPTS {
+        LOCKOUT_SECS
+    } else if failures >= FREE_ATTEMPTS {
+        min(1 << (failures - FREE_ATTEMPTS + 1), LOCKOUT_SECS)
+    } else {
+        0
+    }
+}
+
+impl Vault {
+    /// Authenticates user with credentials, refusing to even check them if either login
+    /// or client address failed too many times recently.
+    pub fn authenticate_throttled(
+        &self,
+        auth: &Option<Authentication>,
+        client: Option<&str>,
+    ) -> DBResult<User> {
+        let mut subjects = Vec::new();
+        if let Some(Authentication::Credentials(login, _)) = auth {
+            subjects.push(("login", login.to_lowercase()));
+        }
+        if let Some(client) = client {
+            subjects.push(("ip", client.to_string()));
+        }
+        self.check_lockout(&subjects)?;
+
+        match self.authenticate_user(auth) {
+            Ok(user) => {
+                // failures of client address are left to expire, otherwise logging into any
+                // owned account in between would let one guess passwords of others endlessly
+                self.get_connection().execute(
+                    "DELETE FROM login_failures WHERE kind = 'login' AND subject = ?1",
+                    params![user.login],
+                )?;
+                Ok(user)
+            }
+            Err(e @ UnknownUser) | Err(e @ BadPassword) => {
+                for (kind, subject) in &subjects {
+                    self.record_failure(kind, subject)?;
+                }
+                Err(e)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    pub fn find_lockouts(&self, subject: Option<&str>) -> DBResult<Vec<Lockout>> {
+        let subject = Query::patternize(subject.unwrap_or_default());
+        Query::new_with_initial(
+            "SELECT kind, subject, failures, failed_at, locked_until FROM login_failures WHERE",
+        )
+        .concat_with_param(
+            "subject LIKE :subject ORDER BY failed_at DESC",
+            (":subject", &subject),
+        )
+        .fetch(self.get_connection())
+    }
+    /// Forgets failed attempts of given login or client address, or all of them
+    /// if none was given. Returns number of cleared records.
+    pub fn clear_lockouts(&self, subject: Option<&str>) -> DBResult<usize> {
+        let conn = self.get_connection();
+        let cleared = match subject {
+            Some(subject) => conn.execute(
+                "DELETE FROM login_failures WHERE subject = ?1",
+                params![subject.to_lowercase()],
+            )?,
+            None => conn.execute("DELETE FROM login_failures", params![])?,
+        };
+        Ok(cleared)
+    }
+    fn check_lockout(&self, subjects: &[(&str, String)]) -> DBResult<()> {
+        let now = now();
+        for (kind, subject) in subjects {
+            let locked_until: Option<String> = self
+                .get_connection()
+                .query_row(
+                    "SELECT locked_until FROM login_failures WHERE kind = ?1 AND subject = ?2",
+                    params![kind, subject],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .flatten();
+
+            match locked_until.as_deref().and_then(unix_timestamp) {
+                Some(until) if until > now => return Err(LockedOut(until - now)),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+    fn record_failure(&self, kind: &str, subject: &str) -> DBResult<()> {
+        let now = now();
+        let conn = self.get_connection();
+        conn.execute(
+            "INSERT INTO login_failures(kind, subject, failures, failed_at) VALUES(?1, ?2, 1, ?3) \
+            ON CONFLICT(kind, subject) DO UPDATE SET \
+            failures = CASE WHEN failed_at < ?4 THEN 1 ELSE failures + 1 END, failed_at = ?3",
+            params![kind, subject, datetime(now), datetime(now - FORGET_SECS)],
+        )?;
+        let failures: u32 = conn.query_row(
+            "SELECT failures FROM login_failures WHERE kind = ?1 AND subject = ?2",
+            params![kind, subject],
+            |row| row.get(0),
+        )?;
+        let delay = backoff(failures);
+        if delay > 0 {
+            conn.execute(
+                "UPDATE login_failures SET locked_until = ?1 WHERE kind = ?2 AND subject = ?3",
+                params![datetime(now + delay), kind, subject],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_lockout {
+    use super::*;
+    use crate::vault::test_db::{auth, vault};
+    use rstest::*;
+
+    #[rstest]
+    #[case(1, 0)]
+    #[case(3, 2)]
+    #[case(5, 8)]
+    #[case(9, 128)]
+    #[case(10, LOCKOUT_SECS)]
+    #[case(50, LOCKOUT_SECS)]
+    fn test_backoff(#[case] failures: u32, #[case] expected: i64) {
+        assert_eq!(expected, backoff(failures));
+    }
+
+    #[rstest]
+    fn test_lockout(vault: &Vault, auth: Option<Authentication>) {
+        let name = match &auth {
+            Some(Authentication::Credentials(login, _)) => login.clone(),
+            _ => unreachable!(),
+        };
+        let (login, client) = (name.to_lowercase(), format!("ip-of-{}", name));
+        let wrong = Authentication::from_credentials(name, "wrong".to_string());
+
+        // failures below the limit are remembered, but don't block anything yet
+        for _ in 1..FREE_ATTEMPTS {
+            assert!(matches!(
+                vault.authenticate_throttled(&wrong, Some(&client)),
+                Err(BadPassword)
+            ));
+        }
+        assert!(vault.authenticate_throttled(&auth, None).is_ok());
+        assert_eq!(
+            vec!["ip"],
+            vault
+                .find_lockouts(Some(&login))
+                .unwrap()
+                .iter()
+                .map(|l| l.kind.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        // successful login from the very same address doesn't forget address failures
+        assert!(vault.authenticate_throttled(&auth, Some(&client)).is_ok());
+        let lockouts = vault.find_lockouts(Some(&client)).unwrap();
+        assert_eq!(1, lockouts.len());
+        assert_eq!(FREE_ATTEMPTS - 1, lockouts[0].failures);
+
+        for _ in 0..FREE_ATTEMPTS {
+            vault.authenticate_throttled(&wrong, None).unwrap_err();
+        }
+        assert!(matches!(
+            vault.authenticate_throttled(&auth, None),
+            Err(LockedOut(_))
+        ));
+        assert_eq!(1, vault.clear_lockouts(Some(&login)).unwrap());
+        assert!(vault.authenticate_throttled(&auth, None).is_ok());
+    }
+}
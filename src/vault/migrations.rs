@@ -60,7 +60,7 @@ impl Vault {
         // ...and compose final transaction
         let final_txn = migrations.iter().fold(String::default(), |mut txn, m| {
             let buf = Asset::get(m.file.as_ref()).unwrap();
-            match str::from_utf8(&buf) {
+            match str::from_utf8(&buf.data) {
                 Ok(s) => {
                     txn.push_str(s);
                     txn.push_str(
@@ -104,8 +104,45 @@ impl Vault {
         if let Some(m) = self.build_migration(base_script_version, app_semver) {
             match self.get_connection().execute_batch(m.as_str()) {
                 Ok(_) => debug!("Upgraded to {}", self.version().unwrap().0),
-                _ => panic!("Couldn't update the database. Bailing out."),
+                Err(e) => panic!("Couldn't update the database ({}). Bailing out.", e),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test_migrations {
+    use super::*;
+    use crate::vault::init_vault;
+    use rstest::*;
+    use rusqlite::params;
+    use tempfile::NamedTempFile;
+
+    #[rstest]
+    fn test_case_variant_logins() {
+        let appver = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+        let vault = init_vault(NamedTempFile::new().unwrap(), appver.clone()).unwrap();
+
+        // roll back to the times when logins differing by case were allowed
+        vault
+            .get_connection()
+            .execute_batch(
+                "DROP INDEX users_login_nocase_idx; \
+                 DELETE FROM migrations WHERE version >= 'V20261017200000'; \
+                 INSERT INTO users(id, login, password) \
+                 VALUES (1, 'bob', ''), (2, 'Bob', ''), (3, 'BOB', ''), (4, 'alice', '');",
+            )
+            .unwrap();
+        vault.upgrade("V20261017190000".to_string(), appver);
+
+        let logins = vault
+            .get_connection()
+            .prepare("SELECT login FROM users ORDER BY id")
+            .unwrap()
+            .query_map(params![], |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(vec!["bob", "Bob-2", "BOB-3", "alice"], logins);
+    }
+}
@@ -1,10 +1,18 @@
 pub mod auth;
+pub mod bulk;
+pub mod export;
+pub mod facets;
+pub mod import;
 pub mod link;
+pub mod lockout;
+pub mod search;
+pub mod session;
+pub mod stored_query;
+pub mod tags;
+pub mod token;
+pub mod user;
 
 mod migrations;
-mod stored_query;
-mod tags;
-mod user;
 
 use super::db::conn_manager;
 
@@ -44,7 +52,9 @@ pub fn init_vault<P: AsRef<Path>>(db: P, app_semver: Version) -> SqliteResult<Va
             "Your app version {} is too old, minimal required version is: {}",
             app_semver, last_app_version
         )
-    } else if last_app_version < app_semver {
+    } else {
+        // even if app version hasn't changed there might be still some
+        // migrations waiting to be applied. upgrade is a no-op otherwise.
         debug!("Upgrading data version to {}", app_semver);
         vault.upgrade(last_script_version, app_semver);
     }
@@ -56,6 +66,8 @@ pub mod test_db {
     use super::*;
     use crate::utils::random_string;
     use crate::vault::auth::Authentication;
+    use crate::vault::link::{Link, Version};
+    use crate::vault::tags::Tag;
     use lazy_static::lazy_static;
     use rstest::*;
     use tempfile::NamedTempFile;
@@ -73,7 +85,7 @@ pub mod test_db {
 
     #[fixture]
     pub fn vault() -> &'static Vault {
-        &*VAULT
+        &VAULT
     }
 
     #[fixture]
@@ -82,8 +94,46 @@ pub mod test_db {
         let name = login.as_ref();
 
         vault().add_user(name, pass).unwrap();
-        vault().generate_key(name).unwrap();
 
         Authentication::from_credentials(name.to_string(), pass.to_owned())
     }
+
+    /// Yet another user, for tests which need two of them.
+    #[fixture]
+    pub fn other() -> Option<Authentication> {
+        auth::default()
+    }
+
+    /// Adds a link named after its url and tagged with given tags. Returns link as stored.
+    pub fn add_tagged(
+        vault: &Vault,
+        auth: &Option<Authentication>,
+        href: &str,
+        tags: &[&str],
+    ) -> Link {
+        let tags = tags.iter().map(|t| t.to_string()).collect();
+        vault
+            .add_link(auth, Link::new(None, href, href, None, Some(tags)))
+            .unwrap();
+        vault.get_link(auth, href).unwrap().unwrap()
+    }
+
+    pub fn tags_of(vault: &Vault, auth: &Option<Authentication>, href: &str) -> Vec<Tag> {
+        vault
+            .get_link(auth, href)
+            .unwrap()
+            .unwrap()
+            .tags
+            .unwrap_or_default()
+    }
+
+    /// Names of links matching the query, sorted alphabetically.
+    pub fn names(vault: &Vault, auth: &Option<Authentication>, query: &str) -> Vec<String> {
+        let (links, _) = vault
+            .query_links(auth, query, Version::unknown(), None)
+            .unwrap();
+        let mut names: Vec<_> = links.into_iter().map(|l| l.name).collect();
+        names.sort_unstable();
+        names
+    }
 }
@@ -0,0 +1,147 @@
+use crate::db::query::Query;
+use crate::vault::link::Direction;
+
+use miniserde::json::{self, Number, Value};
+use rusqlite::types::Value as SqlValue;
+use std::fmt::Write;
+
+/// Position right after the last link of a page.
+///
+/// Cursor remembers sort keys of the last link, so that next page starts strictly after
+/// it, and id of the most recent link visible to user at the time the first page was fetched.
+/// As link ids never decrease, links inserted in the meantime are left out and don't shift
+/// pages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cursor {
+    pub snapshot: i64,
+    pub keys: Vec<SqlValue>,
+}
+
+impl Cursor {
+    pub fn new(snapshot: i64) -> Self {
+        Cursor {
+            snapshot,
+            keys: Vec::new(),
+        }
+    }
+    pub fn set_keys(mut self, keys: Vec<SqlValue>) -> Self {
+        self.keys = keys;
+        self
+    }
+    /// Opaque representation of cursor, hex-encoded json array.
+    pub fn encode(&self) -> String {
+        let mut values = vec![Value::Number(Number::I64(self.snapshot))];
+        values.extend(self.keys.iter().map(|key| match key {
+            SqlValue::Integer(i) => Value::Number(Number::I64(*i)),
+            SqlValue::Real(f) => Value::Number(Number::F64(*f)),
+            SqlValue::Text(s) => Value::String(s.clone()),
+            _ => Value::Null,
+        }));
+        json::to_string(&values)
+            .bytes()
+            .fold(String::new(), |mut out, b| {
+                let _ = write!(out, "{:02x}", b);
+                out
+            })
+    }
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| {
+                cursor
+                    .get(i..i + 2)
+                    .and_then(|b| u8::from_str_radix(b, 16).ok())
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let values: Vec<Value> = json::from_str(&String::from_utf8(bytes).ok()?).ok()?;
+        let mut keys = values.into_iter().map(|value| match value {
+            Value::Number(Number::U64(n)) => Some(SqlValue::Integer(n as i64)),
+            Value::Number(Number::I64(n)) => Some(SqlValue::Integer(n)),
+            Value::Number(Number::F64(f)) => Some(SqlValue::Real(f)),
+            Value::String(s) => Some(SqlValue::Text(s)),
+            Value::Null => Some(SqlValue::Null),
+            _ => None,
+        });
+        match keys.next()?? {
+            SqlValue::Integer(snapshot) => Some(Cursor {
+                snapshot,
+                keys: keys.collect::<Option<_>>()?,
+            }),
+            _ => None,
+        }
+    }
+    /// Compiles condition links following the cursor need to meet, when sorted by given keys.
+    /// Returns None if cursor doesn't fit the keys, eg. was created for a different order.
+    pub fn compile(&self, sort: &[(&str, Direction)], query: &mut Query) -> Option<String> {
+        let snapshot = format!("l.id <= {}", query.bind(self.snapshot));
+        if self.keys.is_empty() {
+            return Some(snapshot);
+        }
+        if self.keys.len() != sort.len() {
+            return None;
+        }
+
+        // (a, b) following (x, y) means: a after x OR (a is x AND b after y). keys are
+        // compared with IS to have NULLs equal to each other.
+
+        let params: Vec<_> = self.keys.iter().map(|k| query.bind(k.clone())).collect();
+        let alternatives: Vec<_> = sort
+            .iter()
+            .enumerate()
+            .map(|(i, (column, direction))| {
+                let mut conditions: Vec<_> = sort[..i]
+                    .iter()
+                    .zip(&params)
+                    .map(|((c, _), p)| format!("{} IS {}", c, p))
+                    .collect();
+                let operator = match direction {
+                    Direction::Ascending => ">",
+                    Direction::Descending => "<",
+                };
+                conditions.push(format!("{} {} {}", column, operator, params[i]));
+                format!("({})", conditions.join(" AND "))
+            })
+            .collect();
+        Some(format!("{} AND ({})", snapshot, alternatives.join(" OR ")))
+    }
+}
+
+#[cfg(test)]
+mod test_cursor {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn test_encode_decode() {
+        let cursor = Cursor::new(42).set_keys(vec![
+            SqlValue::Integer(0),
+            SqlValue::Real(-1.2345678901234),
+            SqlValue::Text("2024-01-01 \"10:00\"".into()),
+            SqlValue::Null,
+        ]);
+        assert_eq!(Some(cursor.clone()), Cursor::decode(&cursor.encode()));
+        assert_eq!(None, Cursor::decode("zz"));
+        assert_eq!(None, Cursor::decode("5b5d"));
+    }
+
+    #[rstest]
+    fn test_compile() {
+        let mut query = Query::new();
+        let cursor = Cursor::new(42).set_keys(vec![
+            SqlValue::Text("2024-01-01".into()),
+            SqlValue::Integer(7),
+        ]);
+        let sort = [
+            ("l.created_at", Direction::Descending),
+            ("l.id", Direction::Descending),
+        ];
+        assert_eq!(
+            Some(
+                "l.id <= :p0 AND ((l.created_at < :p1) OR (l.created_at IS :p1 AND l.id < :p2))"
+                    .to_string()
+            ),
+            cursor.compile(&sort, &mut query)
+        );
+        assert_eq!(None, cursor.compile(&sort[..1], &mut query));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::vault::search::{DateRange, Moment};
+
+/// Parses value of a date term. Accepted values are:
+///
+/// - `never` - date has not been set at all (eg. link has never been read)
+/// - absolute date: `2024`, `2024-03` or `2024-03-15`, meaning entire year, month or day,
+///   optionally preceded by `>`, `>=`, `<` or `<=`
+/// - relative age: a number followed by `h`, `d`, `w`, `m` or `y`, like `7d`, optionally
+///   preceded by `<` (newer than, which is a default) or `>` (older than)
+pub fn parse_range(value: &str) -> Option<DateRange> {
+    if value == "never" {
+        return Some(DateRange::Never);
+    }
+    let (operator, date) = match value.find(|c: char| c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => return None,
+    };
+    if let Some(ago) = parse_age(date) {
+        return match operator {
+            "" | "<" | "<=" => Some(DateRange::Since(ago)),
+            ">" | ">=" => Some(DateRange::Until(ago)),
+            _ => None,
+        };
+    }
+    let (start, period) = parse_date(date)?;
+    let end = Moment::new(&start, Some(period));
+    let start = Moment::new(&start, None);
+
+    match operator {
+        "" => Some(DateRange::Between(start, end)),
+        ">" => Some(DateRange::Since(end)),
+        ">=" => Some(DateRange::Since(start)),
+        "<" => Some(DateRange::Until(start)),
+        "<=" => Some(DateRange::Until(end)),
+        _ => None,
+    }
+}
+
+/// Parses relative age, like `7d`, into a moment in the past.
+fn parse_age(age: &str) -> Option<Moment> {
+    let (number, unit) = age.split_at(age.len() - age.chars().last()?.len_utf8());
+    let number = number.parse::<u32>().ok()?;
+    let modifier = match unit {
+        "h" => format!("-{} hours", number),
+        "d" => format!("-{} days", number),
+        "w" => format!("-{} days", number.checked_mul(7)?),
+        "m" => format!("-{} months", number),
+        "y" => format!("-{} years", number),
+        _ => return None,
+    };
+    Some(Moment::new("now", Some(&modifier)))
+}
+
+/// Parses `YYYY`, `YYYY-MM` or `YYYY-MM-DD` into the first day of given period
+/// and the modifier which shifts that day to the beginning of next period.
+fn parse_date(date: &str) -> Option<(String, &'static str)> {
+    let parts: Vec<_> = date.split('-').collect();
+    let number = |part: &str, len: usize, range: (u32, u32)| {
+        if part.len() == len && part.chars().all(|c| c.is_ascii_digit()) {
+            part.parse::<u32>()
+                .ok()
+                .filter(|n| *n >= range.0 && *n <= range.1)
+        } else {
+            None
+        }
+    };
+    let year = number(parts.first()?, 4, (1, 9999))?;
+    let month = parts.get(1).map(|m| number(m, 2, (1, 12)));
+    let day = parts.get(2).map(|d| number(d, 2, (1, 31)));
+
+    match (month, day, parts.len()) {
+        (None, None, 1) => Some((format!("{:04}-01-01", year), "+1 years")),
+        (Some(Some(m)), None, 2) => Some((format!("{:04}-{:02}-01", year, m), "+1 months")),
+        (Some(Some(m)), Some(Some(d)), 3) => {
+            Some((format!("{:04}-{:02}-{:02}", year, m, d), "+1 days"))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_date {
+    use super::*;
+    use rstest::*;
+
+    fn at(base: &str, modifier: Option<&str>) -> Moment {
+        Moment::new(base, modifier)
+    }
+
+    #[rstest]
+    #[case("never", DateRange::Never)]
+    #[case(
+        "2024",
+        DateRange::Between(at("2024-01-01", None), at("2024-01-01", Some("+1 years")))
+    )]
+    #[case(
+        "2024-03",
+        DateRange::Between(at("2024-03-01", None), at("2024-03-01", Some("+1 months")))
+    )]
+    #[case(">2024-01-01", DateRange::Since(at("2024-01-01", Some("+1 days"))))]
+    #[case(">=2024-01-01", DateRange::Since(at("2024-01-01", None)))]
+    #[case("<2024-01", DateRange::Until(at("2024-01-01", None)))]
+    #[case("<=2024-01", DateRange::Until(at("2024-01-01", Some("+1 months"))))]
+    #[case("<7d", DateRange::Since(at("now", Some("-7 days"))))]
+    #[case("2w", DateRange::Since(at("now", Some("-14 days"))))]
+    #[case(">1y", DateRange::Until(at("now", Some("-1 years"))))]
+    fn test_parse_range(#[case] value: &str, #[case] expected: DateRange) {
+        assert_eq!(Some(expected), parse_range(value));
+    }
+
+    #[rstest]
+    #[case("yesterday")]
+    #[case("2024-13")]
+    #[case("2024-1-1")]
+    #[case("24-01-01")]
+    #[case("2024-01-01-01")]
+    #[case("=2024")]
+    #[case("7x")]
+    #[case("7ż")]
+    #[case("<")]
+    fn test_invalid_range(#[case] value: &str) {
+        assert_eq!(None, parse_range(value));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::vault::search::{SearchError, SearchErrorKind};
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    /// Opening parenthesis
+    LParen,
+    /// Closing parenthesis
+    RParen,
+    /// `AND` operator (optional, terms are joined with AND by default)
+    And,
+    /// `OR` operator
+    Or,
+    /// `NOT` operator
+    Not,
+    /// Free word
+    Word(String),
+    /// "Quoted phrase"
+    Phrase(String),
+    /// Field-scoped term, like `tags:rust` or `href:"https://foo.com"`
+    Field(String, String),
+}
+
+/// Token along with its position (in characters) in the input.
+pub type Spanned = (usize, Token);
+
+pub struct Lexer<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    input: &'a str,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Lexer {
+            chars: input.char_indices().peekable(),
+            input,
+        }
+    }
+    pub fn tokenize(mut self) -> Result<Vec<Spanned>, SearchError> {
+        let mut tokens = Vec::new();
+        while let Some(&(pos, c)) = self.chars.peek() {
+            let position = self.position(pos);
+            match c {
+                c if c.is_whitespace() => {
+                    self.chars.next();
+                }
+                '(' => {
+                    self.chars.next();
+                    tokens.push((position, Token::LParen));
+                }
+                ')' => {
+                    self.chars.next();
+                    tokens.push((position, Token::RParen));
+                }
+                '"' => {
+                    let phrase = self.quoted(position)?;
+                    tokens.push((position, Token::Phrase(phrase)));
+                }
+                _ => {
+                    let word = self.word();
+                    tokens.push((position, self.classify(word, position)?));
+                }
+            }
+        }
+        Ok(tokens)
+    }
+
+    /// Converts byte offset into character position which is what users see.
+    fn position(&self, byte_offset: usize) -> usize {
+        self.input[..byte_offset].chars().count()
+    }
+
+    /// Reads a phrase enclosed in double quotes. Quote character itself
+    /// might be escaped with a backslash.
+    fn quoted(&mut self, position: usize) -> Result<String, SearchError> {
+        let mut phrase = String::new();
+        self.chars.next();
+        while let Some((_, c)) = self.chars.next() {
+            match c {
+                '"' => return Ok(phrase),
+                '\\' => {
+                    if let Some((_, escaped)) = self.chars.next() {
+                        phrase.push(escaped);
+                    }
+                }
+                c => phrase.push(c),
+            }
+        }
+        Err(SearchError::new(position, SearchErrorKind::UnclosedQuote))
+    }
+
+    /// Reads a word up to the first whitespace. Parentheses are allowed within a word
+    /// as long as they are balanced, so that urls like `wiki/Rust_(language)` stay intact,
+    /// but closing parenthesis of a group ends the word.
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        let mut depth = 0;
+        while let Some(&(_, c)) = self.chars.peek() {
+            match c {
+                c if c.is_whitespace() => break,
+                '(' => depth += 1,
+                ')' if depth == 0 => break,
+                ')' => depth -= 1,
+                _ => (),
+            }
+            word.push(c);
+            self.chars.next();
+
+            // quoted value of a field, like desc:"async runtime"
+            if c == ':' && self.next_is_quote() {
+                break;
+            }
+        }
+        word
+    }
+
+    fn classify(&mut self, word: String, position: usize) -> Result<Token, SearchError> {
+        match word.as_str() {
+            "AND" => return Ok(Token::And),
+            "OR" => return Ok(Token::Or),
+            "NOT" => return Ok(Token::Not),
+            _ => (),
+        }
+
+        // field name is a lowercase identifier followed by a colon. anything else, including
+        // urls like https://foo.com, is treated as a regular word.
+
+        match word.split_once(':') {
+            Some((field, value))
+                if !field.is_empty()
+                    && field.chars().all(|c| c.is_ascii_lowercase())
+                    && !value.starts_with("//") =>
+            {
+                let value = if value.is_empty() && self.next_is_quote() {
+                    self.quoted(position + field.chars().count() + 1)?
+                } else {
+                    value.to_string()
+                };
+                Ok(Token::Field(field.to_string(), value))
+            }
+            _ => Ok(Token::Word(word)),
+        }
+    }
+
+    fn next_is_quote(&mut self) -> bool {
+        matches!(self.chars.peek(), Some((_, '"')))
+    }
+}
+
+#[cfg(test)]
+mod test_lexer {
+    use super::*;
+    use rstest::*;
+
+    fn tokens(input: &str) -> Vec<Token> {
+        Lexer::new(input)
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|(_, t)| t)
+            .collect()
+    }
+
+    fn word(w: &str) -> Token {
+        Token::Word(w.to_string())
+    }
+
+    fn field(f: &str, v: &str) -> Token {
+        Token::Field(f.to_string(), v.to_string())
+    }
+
+    #[rstest]
+    #[case("rust async", vec![word("rust"), word("async")])]
+    #[case("\"async book\" rust", vec![Token::Phrase("async book".into()), word("rust")])]
+    #[case("\"say \\\"hi\\\"\"", vec![Token::Phrase("say \"hi\"".into())])]
+    #[case("href:https://foo.com/a:b", vec![field("href", "https://foo.com/a:b")])]
+    #[case("desc:\"async book\"", vec![field("desc", "async book")])]
+    #[case("https://foo.com", vec![word("https://foo.com")])]
+    #[case("(a OR b)", vec![Token::LParen, word("a"), Token::Or, word("b"), Token::RParen])]
+    #[case("NOT tags:a,-b", vec![Token::Not, field("tags", "a,-b")])]
+    #[case("(href:wiki/Rust_(language))", vec![Token::LParen, field("href", "wiki/Rust_(language)"), Token::RParen])]
+    #[case("or and not", vec![word("or"), word("and"), word("not")])]
+    fn test_tokenize(#[case] input: &str, #[case] expected: Vec<Token>) {
+        assert_eq!(expected, tokens(input));
+    }
+
+    #[rstest]
+    fn test_positions_count_characters() {
+        let spans: Vec<_> = Lexer::new("żółw (a)")
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect();
+
+        assert_eq!(vec![0, 5, 6, 7], spans);
+    }
+
+    #[rstest]
+    #[case("\"unclosed", 0)]
+    #[case("rust desc:\"unclosed", 10)]
+    fn test_unclosed_quote(#[case] input: &str, #[case] position: usize) {
+        let err = Lexer::new(input).tokenize().unwrap_err();
+
+        assert_eq!(SearchErrorKind::UnclosedQuote, err.kind);
+        assert_eq!(position, err.position);
+    }
+}
@@ -0,0 +1,178 @@
+//! Query language used to look for links.
+//!
+//! A query is a sequence of terms, implicitly joined with `AND`. A term is either a free word,
+//! a "quoted phrase" or a field-scoped term like `tags:rust,-doc`, `href:github.com`,
+//! `name:tokio`, `desc:"async runtime"` or `flags:toread`. Terms might be combined with
+//! `OR`, negated with `NOT` and grouped with parentheses. Dates might be constrained with
+//! `created:`, `updated:` and `read:` terms, eg. `created:>2024-01-01` or `updated:<7d`.
+//! Additionally, `sort:` modifier decides on order of returned links.
+
+pub mod cursor;
+mod date;
+mod lexer;
+mod parser;
+mod sql;
+
+use crate::vault::link::SortOrder;
+use crate::vault::tags::Tag;
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Flag {
+    ToRead,
+    Shared,
+    Favourite,
+}
+
+impl FromStr for Flag {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toread" => Ok(Flag::ToRead),
+            "shared" => Ok(Flag::Shared),
+            "fav" | "favourite" | "favorite" => Ok(Flag::Favourite),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DateField {
+    Created,
+    Updated,
+    Read,
+}
+
+/// Point in time expressed as SQLite datetime base (a date or "now") shifted by optional modifier.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Moment {
+    pub base: String,
+    pub modifier: Option<String>,
+}
+
+impl Moment {
+    pub fn new(base: &str, modifier: Option<&str>) -> Self {
+        Moment {
+            base: base.to_string(),
+            modifier: modifier.map(String::from),
+        }
+    }
+}
+
+/// Range of dates, inclusive at the beginning and exclusive at the end.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DateRange {
+    Never,
+    Since(Moment),
+    Until(Moment),
+    Between(Moment, Moment),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term {
+    /// Free word, matched by prefix against name, description and url
+    Text(String),
+    /// Quoted phrase, matched against name, description and url
+    Phrase(String),
+    /// Text matched against name only
+    Name(String),
+    /// Text matched against description only
+    Description(String),
+    /// Part of url
+    Href(String),
+    /// Exact url, as opposed to [`Term::Href`]
+    Url(String),
+    /// Optional, +required and -excluded tags
+    Tags(Vec<Tag>),
+    /// Flags which all need to be set
+    Flags(Vec<Flag>),
+    /// Date of creation, last update or read falling into given range
+    Date(DateField, DateRange),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Term(Term),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+/// Parsed query - an expression deciding which links to return along with their order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Search {
+    pub expr: Option<Expr>,
+    pub sort: SortOrder,
+}
+
+impl Search {
+    pub fn new(expr: Option<Expr>) -> Self {
+        Search {
+            expr,
+            sort: SortOrder::Relevance,
+        }
+    }
+    /// Search for a link with exactly given url.
+    pub fn url(href: &str) -> Self {
+        Search::new(Some(Expr::Term(Term::Url(href.to_string()))))
+    }
+    pub fn set_sort(mut self, sort: SortOrder) -> Self {
+        self.sort = sort;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchErrorKind {
+    UnclosedQuote,
+    UnbalancedParenthesis,
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    UnknownField(String),
+    EmptyValue(String),
+    UnknownFlag(String),
+    UnknownSort(String),
+    BadDate(String),
+    MisplacedModifier(String),
+}
+
+/// Error reported when query cannot be parsed. Position is a character offset
+/// of the input where the problem has been spotted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchError {
+    pub position: usize,
+    pub kind: SearchErrorKind,
+}
+
+impl SearchError {
+    pub fn new(position: usize, kind: SearchErrorKind) -> Self {
+        SearchError { position, kind }
+    }
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            SearchErrorKind::UnclosedQuote => write!(f, "unclosed quote"),
+            SearchErrorKind::UnbalancedParenthesis => write!(f, "unbalanced parenthesis"),
+            SearchErrorKind::UnexpectedToken(t) => write!(f, "unexpected '{}'", t),
+            SearchErrorKind::UnexpectedEnd => write!(f, "unexpected end of query"),
+            SearchErrorKind::UnknownField(field) => write!(f, "unknown field '{}'", field),
+            SearchErrorKind::EmptyValue(field) => write!(f, "missing value of '{}'", field),
+            SearchErrorKind::UnknownFlag(flag) => write!(f, "unknown flag '{}'", flag),
+            SearchErrorKind::UnknownSort(sort) => write!(f, "unknown sort order '{}'", sort),
+            SearchErrorKind::BadDate(date) => write!(f, "invalid date '{}'", date),
+            SearchErrorKind::MisplacedModifier(m) => write!(f, "'{}' cannot be used here", m),
+        }?;
+        write!(f, " at position {}", self.position)
+    }
+}
+
+/// Parses a query into its [`Search`] representation.
+pub fn parse(input: &str) -> Result<Search, SearchError> {
+    let tokens = lexer::Lexer::new(input).tokenize()?;
+    parser::Parser::new(tokens, input.chars().count()).parse()
+}
@@ -0,0 +1,338 @@
+use crate::vault::link::{Direction, SortKey, SortOrder};
+use crate::vault::search::date::parse_range;
+use crate::vault::search::lexer::{Spanned, Token};
+use crate::vault::search::{DateField, Expr, Flag, Search, SearchError, SearchErrorKind, Term};
+
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+/// Recursive descent parser of following grammar:
+///
+/// ```text
+/// query   := or_expr
+/// or_expr := and_expr ("OR" and_expr)*
+/// and_expr:= unary ("AND"? unary)*
+/// unary   := "NOT" unary | primary
+/// primary := "(" or_expr ")" | term
+/// ```
+///
+/// Modifiers (like `sort:`) are not the part of expression, they are collected
+/// aside while parsing and yield no expression.
+pub struct Parser {
+    tokens: Peekable<IntoIter<Spanned>>,
+    end: usize,
+    sort: Option<SortOrder>,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Spanned>, end: usize) -> Self {
+        Parser {
+            tokens: tokens.into_iter().peekable(),
+            end,
+            sort: None,
+        }
+    }
+    pub fn parse(mut self) -> Result<Search, SearchError> {
+        let expr = if self.tokens.peek().is_some() {
+            self.or_expr()?
+        } else {
+            None
+        };
+        if let Some((position, token)) = self.tokens.next() {
+            return Err(match token {
+                Token::RParen => SearchError::new(position, SearchErrorKind::UnbalancedParenthesis),
+                t => SearchError::new(position, unexpected(&t)),
+            });
+        }
+        Ok(Search::new(expr).set_sort(self.sort.unwrap_or(SortOrder::Relevance)))
+    }
+    fn or_expr(&mut self) -> Result<Option<Expr>, SearchError> {
+        let position = self.position();
+        let mut exprs = vec![self.and_expr()?];
+        while let Some((_, Token::Or)) = self.tokens.peek() {
+            self.tokens.next();
+            exprs.push(self.and_expr()?);
+        }
+        if exprs.len() == 1 {
+            return Ok(exprs.pop().unwrap());
+        }
+        exprs
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .map(|e| Some(Expr::Or(e)))
+            .ok_or_else(|| {
+                SearchError::new(position, SearchErrorKind::MisplacedModifier("sort".into()))
+            })
+    }
+    fn and_expr(&mut self) -> Result<Option<Expr>, SearchError> {
+        let mut exprs = Vec::new();
+        if let Some(e) = self.unary()? {
+            exprs.push(e);
+        }
+        loop {
+            match self.tokens.peek() {
+                Some((_, Token::And)) => {
+                    self.tokens.next();
+                }
+                Some((_, Token::Or)) | Some((_, Token::RParen)) | None => break,
+                _ => (),
+            }
+            if let Some(e) = self.unary()? {
+                exprs.push(e);
+            }
+        }
+        Ok(match exprs.len() {
+            0 => None,
+            1 => exprs.pop(),
+            _ => Some(Expr::And(exprs)),
+        })
+    }
+    fn unary(&mut self) -> Result<Option<Expr>, SearchError> {
+        if let Some((position, Token::Not)) = self.tokens.peek() {
+            let position = *position;
+            self.tokens.next();
+            return match self.unary()? {
+                Some(e) => Ok(Some(Expr::Not(Box::new(e)))),
+                None => Err(SearchError::new(
+                    position,
+                    SearchErrorKind::MisplacedModifier("sort".into()),
+                )),
+            };
+        }
+        self.primary()
+    }
+    fn primary(&mut self) -> Result<Option<Expr>, SearchError> {
+        match self.tokens.next() {
+            Some((position, Token::LParen)) => {
+                let expr = self.or_expr()?;
+                match self.tokens.next() {
+                    Some((_, Token::RParen)) => Ok(expr),
+                    Some((p, t)) => Err(SearchError::new(p, unexpected(&t))),
+                    None => Err(SearchError::new(
+                        position,
+                        SearchErrorKind::UnbalancedParenthesis,
+                    )),
+                }
+            }
+            Some((_, Token::Word(w))) => Ok(Some(Expr::Term(Term::Text(w)))),
+            Some((_, Token::Phrase(p))) => Ok(Some(Expr::Term(Term::Phrase(p)))),
+            Some((position, Token::Field(field, value))) => self.field(position, field, value),
+            Some((position, Token::RParen)) => Err(SearchError::new(
+                position,
+                SearchErrorKind::UnbalancedParenthesis,
+            )),
+            Some((position, t)) => Err(SearchError::new(position, unexpected(&t))),
+            None => Err(SearchError::new(self.end, SearchErrorKind::UnexpectedEnd)),
+        }
+    }
+    fn field(
+        &mut self,
+        position: usize,
+        field: String,
+        value: String,
+    ) -> Result<Option<Expr>, SearchError> {
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            return Err(SearchError::new(
+                position,
+                SearchErrorKind::EmptyValue(field),
+            ));
+        }
+        let term = match field.as_str() {
+            "tags" => Term::Tags(
+                value
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+            ),
+            "flags" => Term::Flags(
+                value
+                    .split(',')
+                    .map(|f| {
+                        f.trim().parse::<Flag>().map_err(|_| {
+                            SearchError::new(
+                                position,
+                                SearchErrorKind::UnknownFlag(f.trim().to_string()),
+                            )
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            "href" => Term::Href(value),
+            "name" => Term::Name(value),
+            "desc" => Term::Description(value),
+            "created" | "updated" | "read" => {
+                let date_field = match field.as_str() {
+                    "created" => DateField::Created,
+                    "updated" => DateField::Updated,
+                    _ => DateField::Read,
+                };
+                match parse_range(&value) {
+                    Some(range) => Term::Date(date_field, range),
+                    None => {
+                        return Err(SearchError::new(position, SearchErrorKind::BadDate(value)))
+                    }
+                }
+            }
+            "sort" => {
+                // sorting key prefixed with "-" reverses the order, eg. sort:-created
+                // returns the most recent links first.
+
+                let (direction, key) = match value.strip_prefix('-') {
+                    Some(key) => (Direction::Descending, key),
+                    None => (Direction::Ascending, value.as_str()),
+                };
+                self.sort = Some(match (key, direction) {
+                    ("relevance", Direction::Ascending) => SortOrder::Relevance,
+                    ("created", d) => SortOrder::By(SortKey::Created, d),
+                    ("updated", d) => SortOrder::By(SortKey::Updated, d),
+                    ("name", d) => SortOrder::By(SortKey::Name, d),
+                    _ => {
+                        return Err(SearchError::new(
+                            position,
+                            SearchErrorKind::UnknownSort(value),
+                        ))
+                    }
+                });
+                return Ok(None);
+            }
+            _ => {
+                return Err(SearchError::new(
+                    position,
+                    SearchErrorKind::UnknownField(field),
+                ))
+            }
+        };
+        Ok(Some(Expr::Term(term)))
+    }
+    fn position(&mut self) -> usize {
+        self.tokens.peek().map_or(self.end, |(p, _)| *p)
+    }
+}
+
+fn unexpected(token: &Token) -> SearchErrorKind {
+    SearchErrorKind::UnexpectedToken(
+        match token {
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::And => "AND",
+            Token::Or => "OR",
+            Token::Not => "NOT",
+            Token::Word(w) | Token::Phrase(w) => w,
+            Token::Field(f, _) => f,
+        }
+        .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod test_parser {
+    use crate::vault::link::{Direction, SortKey, SortOrder};
+    use crate::vault::search::{parse, DateField, DateRange, Expr, Flag, SearchErrorKind, Term};
+    use rstest::*;
+
+    fn text(t: &str) -> Expr {
+        Expr::Term(Term::Text(t.to_string()))
+    }
+
+    fn tags(t: &[&str]) -> Expr {
+        Expr::Term(Term::Tags(t.iter().map(|t| t.to_string()).collect()))
+    }
+
+    #[rstest]
+    fn test_empty_query() {
+        let search = parse("  ").unwrap();
+
+        assert_eq!(None, search.expr);
+        assert_eq!(SortOrder::Relevance, search.sort);
+    }
+
+    #[rstest]
+    #[case("rust", text("rust"))]
+    #[case("rust async", Expr::And(vec![text("rust"), text("async")]))]
+    #[case("rust AND async", Expr::And(vec![text("rust"), text("async")]))]
+    #[case("rust OR go", Expr::Or(vec![text("rust"), text("go")]))]
+    #[case("NOT go", Expr::Not(Box::new(text("go"))))]
+    #[case("\"async book\"", Expr::Term(Term::Phrase("async book".into())))]
+    #[case("a b OR c", Expr::Or(vec![Expr::And(vec![text("a"), text("b")]), text("c")]))]
+    #[case("a (b OR c)", Expr::And(vec![text("a"), Expr::Or(vec![text("b"), text("c")])]))]
+    #[case("NOT (a OR b)", Expr::Not(Box::new(Expr::Or(vec![text("a"), text("b")]))))]
+    #[case("((a))", text("a"))]
+    fn test_boolean_expressions(#[case] input: &str, #[case] expected: Expr) {
+        assert_eq!(Some(expected), parse(input).unwrap().expr);
+    }
+
+    #[rstest]
+    #[case("tags:rust,-doc", tags(&["rust", "-doc"]))]
+    #[case("tags:rust tags:+web", Expr::And(vec![tags(&["rust"]), tags(&["+web"])]))]
+    #[case("href:https://foo.com:8080/a", Expr::Term(Term::Href("https://foo.com:8080/a".into())))]
+    #[case("name:tokio", Expr::Term(Term::Name("tokio".into())))]
+    #[case("desc:\"async runtime\"", Expr::Term(Term::Description("async runtime".into())))]
+    #[case("flags:toread,fav", Expr::Term(Term::Flags(vec![Flag::ToRead, Flag::Favourite])))]
+    #[case("flags:toread flags:shared", Expr::And(vec![
+        Expr::Term(Term::Flags(vec![Flag::ToRead])),
+        Expr::Term(Term::Flags(vec![Flag::Shared]))
+    ]))]
+    #[case("https://foo.com", text("https://foo.com"))]
+    fn test_field_terms(#[case] input: &str, #[case] expected: Expr) {
+        assert_eq!(Some(expected), parse(input).unwrap().expr);
+    }
+
+    #[rstest]
+    #[case(
+        "rust sort:created",
+        SortOrder::By(SortKey::Created, Direction::Ascending)
+    )]
+    #[case(
+        "rust sort:-created",
+        SortOrder::By(SortKey::Created, Direction::Descending)
+    )]
+    #[case(
+        "sort:updated rust",
+        SortOrder::By(SortKey::Updated, Direction::Ascending)
+    )]
+    #[case("rust sort:name", SortOrder::By(SortKey::Name, Direction::Ascending))]
+    #[case("rust sort:relevance", SortOrder::Relevance)]
+    fn test_sort_modifier(#[case] input: &str, #[case] expected: SortOrder) {
+        let search = parse(input).unwrap();
+
+        assert_eq!(Some(text("rust")), search.expr);
+        assert_eq!(expected, search.sort);
+    }
+
+    #[rstest]
+    fn test_date_terms() {
+        let search = parse("created:>2024-01-01 read:never").unwrap();
+
+        assert!(matches!(
+            search.expr,
+            Some(Expr::And(terms)) if matches!(
+                terms.as_slice(),
+                [Expr::Term(Term::Date(DateField::Created, DateRange::Since(_))),
+                 Expr::Term(Term::Date(DateField::Read, DateRange::Never))]
+            )
+        ));
+    }
+
+    #[rstest]
+    #[case("foo:bar", 0, SearchErrorKind::UnknownField("foo".into()))]
+    #[case("rust tags:", 5, SearchErrorKind::EmptyValue("tags".into()))]
+    #[case("flags:later", 0, SearchErrorKind::UnknownFlag("later".into()))]
+    #[case("sort:random", 0, SearchErrorKind::UnknownSort("random".into()))]
+    #[case("sort:-relevance", 0, SearchErrorKind::UnknownSort("-relevance".into()))]
+    #[case("rust updated:yesterday", 5, SearchErrorKind::BadDate("yesterday".into()))]
+    #[case("(rust", 0, SearchErrorKind::UnbalancedParenthesis)]
+    #[case("rust)", 4, SearchErrorKind::UnbalancedParenthesis)]
+    #[case("rust OR", 7, SearchErrorKind::UnexpectedEnd)]
+    #[case("OR rust", 0, SearchErrorKind::UnexpectedToken("OR".into()))]
+    #[case("NOT", 3, SearchErrorKind::UnexpectedEnd)]
+    #[case("NOT sort:created", 0, SearchErrorKind::MisplacedModifier("sort".into()))]
+    #[case("\"rust", 0, SearchErrorKind::UnclosedQuote)]
+    fn test_errors(#[case] input: &str, #[case] position: usize, #[case] kind: SearchErrorKind) {
+        let err = parse(input).unwrap_err();
+
+        assert_eq!(kind, err.kind);
+        assert_eq!(position, err.position);
+    }
+}
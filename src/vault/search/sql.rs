@@ -0,0 +1,257 @@
+use crate::db::query::Query;
+use crate::utils::path;
+use crate::vault::link::{Direction, SortKey, SortOrder};
+use crate::vault::search::{DateField, DateRange, Expr, Flag, Moment, Search, Term};
+use crate::vault::tags::TagAliases;
+
+impl Search {
+    /// Joins links with full-text index to rank them by relevance.
+    ///
+    /// Ranking is based on all the textual terms which are not negated. Returns false
+    /// if there was no such a term, so there is nothing to rank links by.
+    pub fn compile_ranking(&self, query: &mut Query) -> bool {
+        let mut texts = Vec::new();
+        if let Some(expr) = &self.expr {
+            expr.collect_texts(&mut texts);
+        }
+        if texts.is_empty() {
+            return false;
+        }
+        let param = query.bind(
+            texts
+                .into_iter()
+                .map(|t| format!("({})", t))
+                .collect::<Vec<_>>()
+                .join(" OR "),
+        );
+        query.concat_owned(format!(
+            "LEFT JOIN (\
+             SELECT rowid AS fts_id, rank AS fts_rank \
+             FROM links_fts WHERE links_fts MATCH {}) f ON f.fts_id = l.id",
+            param
+        ));
+        true
+    }
+
+    /// Compiles search expression into a condition links need to meet. Condition is
+    /// expected to be placed in a HAVING clause of query grouping links by their ids,
+    /// with `tagz` being a comma-separated list of link's tags.
+    ///
+    /// Tags are matched along with their aliases.
+    pub fn compile_filter(&self, query: &mut Query, aliases: &TagAliases) {
+        if let Some(expr) = &self.expr {
+            let condition = expr.compile(query, aliases);
+            query.concat_owned(format!("HAVING {}", condition));
+        }
+    }
+
+    /// Keys links are sorted by, as requested by `sort:` modifier. Links get ordered by relevance
+    /// if they have been [ranked](Search::compile_ranking), by creation time (most recent first)
+    /// otherwise. Link id comes last, so that order is always deterministic.
+    pub fn sort_keys(&self, is_ranked: bool) -> Vec<(&'static str, Direction)> {
+        use Direction::{Ascending, Descending};
+
+        match self.sort {
+            SortOrder::Relevance if is_ranked => vec![
+                ("f.fts_rank IS NULL", Ascending),
+                ("f.fts_rank", Ascending),
+                ("l.created_at", Descending),
+                ("l.id", Descending),
+            ],
+            SortOrder::Relevance => vec![
+                ("l.created_at", Descending),
+                ("l.is_favourite", Descending),
+                ("l.id", Descending),
+            ],
+            SortOrder::By(key, direction) => {
+                let column = match key {
+                    SortKey::Created => "l.created_at",
+                    SortKey::Updated => "ifnull(l.updated_at, l.created_at)",
+                    SortKey::Name => "l.name COLLATE NOCASE",
+                };
+                vec![(column, direction), ("l.id", direction)]
+            }
+        }
+    }
+
+    /// Orders links by their [sort keys](Search::sort_keys).
+    pub fn compile_order(&self, query: &mut Query, is_ranked: bool) {
+        let keys: Vec<_> = self
+            .sort_keys(is_ranked)
+            .into_iter()
+            .map(|(column, direction)| match direction {
+                Direction::Ascending => format!("{} ASC", column),
+                Direction::Descending => format!("{} DESC", column),
+            })
+            .collect();
+        query.concat_owned(format!("ORDER BY {}", keys.join(", ")));
+    }
+}
+
+impl Expr {
+    fn compile(&self, query: &mut Query, aliases: &TagAliases) -> String {
+        match self {
+            Expr::Term(term) => term.compile(query, aliases),
+            Expr::Not(expr) => format!("NOT {}", expr.compile(query, aliases)),
+            Expr::And(exprs) => Expr::compile_all(exprs, " AND ", query, aliases),
+            Expr::Or(exprs) => Expr::compile_all(exprs, " OR ", query, aliases),
+        }
+    }
+    fn compile_all(
+        exprs: &[Expr],
+        operator: &str,
+        query: &mut Query,
+        aliases: &TagAliases,
+    ) -> String {
+        let compiled: Vec<_> = exprs.iter().map(|e| e.compile(query, aliases)).collect();
+        format!("({})", compiled.join(operator))
+    }
+    fn collect_texts(&self, texts: &mut Vec<String>) {
+        match self {
+            Expr::Term(term) => {
+                if let Some(text) = term.match_expression() {
+                    texts.push(text);
+                }
+            }
+            Expr::And(exprs) | Expr::Or(exprs) => {
+                exprs.iter().for_each(|e| e.collect_texts(texts));
+            }
+            Expr::Not(_) => (),
+        }
+    }
+}
+
+impl Term {
+    /// Full-text index match expression for textual terms.
+    fn match_expression(&self) -> Option<String> {
+        let (columns, value) = match self {
+            Term::Text(word) => return non_empty(Query::match_expression(word)),
+            Term::Phrase(phrase) => return non_empty(phrase_expression(phrase)),
+            Term::Name(value) => ("name", value),
+            Term::Description(value) => ("description", value),
+            _ => return None,
+        };
+        let expression = if value.contains(char::is_whitespace) {
+            phrase_expression(value)
+        } else {
+            Query::match_expression(value)
+        };
+        non_empty(expression).map(|e| format!("{{{}}} : ({})", columns, e))
+    }
+    fn compile(&self, query: &mut Query, aliases: &TagAliases) -> String {
+        if let Some(expression) = self.match_expression() {
+            let param = query.bind(expression);
+            return format!(
+                "l.id IN (SELECT rowid FROM links_fts WHERE links_fts MATCH {})",
+                param
+            );
+        }
+        match self {
+            Term::Href(href) => {
+                let param = query.bind(Query::patternize(&path(href)));
+                format!("path(href) LIKE {}", param)
+            }
+            Term::Url(href) => {
+                let param = query.bind(path(href));
+                format!("path(href) LIKE {}", param)
+            }
+            Term::Tags(tags) => {
+                // any optional, all +required and no -excluded tag (or any of its aliases) matches.
+                let (optional, required, excluded) = aliases.classify(tags.to_owned());
+                let (required, alternatives): (Vec<_>, Vec<_>) =
+                    required.into_iter().partition(|group| group.len() == 1);
+                let required: Vec<_> = required.into_iter().flatten().collect();
+                let mut conditions = Vec::new();
+
+                if !optional.is_empty() {
+                    let param = query.bind(optional.join(","));
+                    conditions.push(format!("some(ifnull(tagz, ''), {})", param));
+                }
+                if !required.is_empty() {
+                    let param = query.bind(required.join(","));
+                    conditions.push(format!("every(ifnull(tagz, ''), {})", param));
+                }
+                for group in alternatives {
+                    let param = query.bind(group.join(","));
+                    conditions.push(format!("some(ifnull(tagz, ''), {})", param));
+                }
+                if !excluded.is_empty() {
+                    let param = query.bind(excluded.join(","));
+                    conditions.push(format!("NOT some(ifnull(tagz, ''), {})", param));
+                }
+                all_of(conditions)
+            }
+            Term::Flags(flags) => all_of(
+                flags
+                    .iter()
+                    .map(|f| {
+                        match f {
+                            Flag::ToRead => "l.is_toread",
+                            Flag::Shared => "l.is_shared",
+                            Flag::Favourite => "l.is_favourite",
+                        }
+                        .to_string()
+                    })
+                    .collect(),
+            ),
+            Term::Date(field, range) => {
+                // links which have never been updated are considered updated when created.
+
+                let column = match field {
+                    DateField::Created => "l.created_at",
+                    DateField::Updated => "ifnull(l.updated_at, l.created_at)",
+                    DateField::Read => "l.read_at",
+                };
+                match range {
+                    DateRange::Never => format!("{} IS NULL", column),
+                    DateRange::Since(since) => format!("{} >= {}", column, since.compile(query)),
+                    DateRange::Until(until) => format!("{} < {}", column, until.compile(query)),
+                    DateRange::Between(since, until) => format!(
+                        "({} >= {} AND {} < {})",
+                        column,
+                        since.compile(query),
+                        column,
+                        until.compile(query)
+                    ),
+                }
+            }
+
+            // textual terms which have nothing to be matched by, like punctuation, match everything.
+            _ => "1".to_string(),
+        }
+    }
+}
+
+impl Moment {
+    fn compile(&self, query: &mut Query) -> String {
+        let base = query.bind(self.base.clone());
+        match &self.modifier {
+            Some(modifier) => format!("datetime({}, {})", base, query.bind(modifier.clone())),
+            None => format!("datetime({})", base),
+        }
+    }
+}
+
+fn phrase_expression(phrase: &str) -> String {
+    if phrase.chars().any(char::is_alphanumeric) {
+        format!("\"{}\"", phrase.replace('"', "\"\""))
+    } else {
+        String::default()
+    }
+}
+
+fn non_empty(expression: String) -> Option<String> {
+    if expression.is_empty() {
+        None
+    } else {
+        Some(expression)
+    }
+}
+
+fn all_of(conditions: Vec<String>) -> String {
+    if conditions.is_empty() {
+        "1".to_string()
+    } else {
+        format!("({})", conditions.join(" AND "))
+    }
+}
@@ -0,0 +1,246 @@
+use crate::db::DBError::Unauthenticated;
+use crate::db::DBResult;
+use crate::utils::{datetime, now, random_string};
+use crate::vault::auth::Authentication;
+use crate::vault::token::digest;
+use crate::vault::user::User;
+use crate::vault::Vault;
+
+use hmac::{Hmac, Mac, NewMac};
+use miniserde::Serialize;
+use rusqlite::{params, OptionalExtension};
+use sha2::Sha256;
+
+/// How long access token stays valid, in seconds.
+const ACCESS_TTL: i64 = 15 * 60;
+
+/// How long refresh token stays valid, in seconds.
+const REFRESH_TTL: i64 = 30 * 24 * 60 * 60;
+
+/// Pair of tokens issued when user logs in. Short-lived access token authenticates requests,
+/// refresh token is used to get a new pair once access token expires.
+#[derive(Serialize, Clone, Debug)]
+pub struct Session {
+    pub login: String,
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
+fn mac(key: &[u8], message: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("Key of any size expected");
+    mac.update(message.as_bytes());
+    mac
+}
+
+/// Hex-encoded HMAC-SHA256 signature of given message.
+fn sign(key: &[u8], message: &str) -> String {
+    format!("{:x}", mac(key, message).finalize().into_bytes())
+}
+
+/// Checks hex-encoded signature of given message, in constant time not to leak
+/// how much of the signature was correct.
+fn verify(key: &[u8], message: &str, signature: &str) -> bool {
+    let bytes = (0..signature.len())
+        .step_by(2)
+        .map(|i| {
+            signature
+                .get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+        })
+        .collect::<Option<Vec<_>>>();
+    match bytes {
+        Some(bytes) => mac(key, message).verify(&bytes).is_ok(),
+        None => false,
+    }
+}
+
+impl Vault {
+    /// Logs user in with credentials, starting a new session. Failed attempts are throttled
+    /// per login and per client address, if one was provided.
+    pub fn login(&self, auth: &Option<Authentication>, client: Option<&str>) -> DBResult<Session> {
+        let user = self.authenticate_throttled(auth, client)?;
+        let session = self.new_session(user.login)?;
+        let conn = self.get_connection();
+        conn.execute(
+            "DELETE FROM sessions WHERE refresh_expires_at < CURRENT_TIMESTAMP",
+            params![],
+        )?;
+        conn.execute(
+            "INSERT INTO sessions(user_id, access_hash, refresh_hash, access_expires_at, refresh_expires_at) \
+            VALUES(?1, ?2, ?3, ?4, ?5)",
+            params![
+                user.id,
+                digest(&session.token),
+                digest(&session.refresh_token),
+                datetime(now() + ACCESS_TTL),
+                datetime(now() + REFRESH_TTL)
+            ],
+        )?;
+        Ok(session)
+    }
+    /// Rotates tokens of a session given refresh token belongs to. Refresh token
+    /// can be used only once, previously issued access token stops working too.
+    pub fn refresh_session(&self, refresh_token: &str) -> DBResult<Session> {
+        let conn = self.get_connection();
+        let session = self.new_session(String::new())?;
+
+        // tokens are swapped with a single statement, so that refresh token used
+        // concurrently rotates the session only once.
+
+        let user_id: Option<i64> = conn
+            .query_row(
+                "UPDATE sessions SET access_hash = ?1, refresh_hash = ?2, \
+                access_expires_at = ?3, refresh_expires_at = ?4, refreshed_at = CURRENT_TIMESTAMP \
+                WHERE refresh_hash = ?5 AND refresh_expires_at > CURRENT_TIMESTAMP \
+                AND user_id IN (SELECT id FROM users WHERE NOT is_disabled) \
+                RETURNING user_id",
+                params![
+                    digest(&session.token),
+                    digest(&session.refresh_token),
+                    datetime(now() + ACCESS_TTL),
+                    datetime(now() + REFRESH_TTL),
+                    digest(refresh_token)
+                ],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let user_id = user_id.ok_or(Unauthenticated)?;
+        let login: String = conn.query_row(
+            "SELECT login FROM users WHERE id = ?1",
+            params![user_id],
+            |row| row.get(0),
+        )?;
+        Ok(Session {
+            login: login.to_ascii_lowercase(),
+            ..session
+        })
+    }
+    /// Ends a session given access token belongs to.
+    pub fn logout(&self, auth: &Option<Authentication>) -> DBResult<()> {
+        match auth {
+            Some(Authentication::Token(token)) if self.session_user(token.as_str())?.is_some() => {
+                self.get_connection().execute(
+                    "DELETE FROM sessions WHERE access_hash = ?1",
+                    params![digest(token.as_str())],
+                )?;
+                Ok(())
+            }
+            _ => Err(Unauthenticated),
+        }
+    }
+    /// Looks up owner of an access token. Tokens which are not signed like access tokens
+    /// give None, expired or revoked ones are refused.
+    pub fn session_user(&self, token: &str) -> DBResult<Option<User>> {
+        let parts: Vec<_> = token.split('.').collect();
+        let (expires, signature) = match parts.as_slice() {
+            [expires, _nonce, signature] => (expires.parse::<i64>().unwrap_or(0), signature),
+            _ => return Ok(None),
+        };
+        let payload = &token[..token.len() - signature.len() - 1];
+        if !verify(self.signing_key()?.as_bytes(), payload, signature) {
+            return Ok(None);
+        }
+        if expires <= now() {
+            return Err(Unauthenticated);
+        }
+        self.get_connection()
+            .query_row(
+                "SELECT u.id, u.login FROM sessions s INNER JOIN users u ON s.user_id = u.id \
+                WHERE s.access_hash = ?1 AND NOT u.is_disabled",
+                params![digest(token)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .map_or(Err(Unauthenticated), |user: (i64, String)| {
+                Ok(Some(User::new(user.0, &user.1)))
+            })
+    }
+    /// Generates a new pair of tokens, not stored anywhere yet.
+    fn new_session(&self, login: String) -> DBResult<Session> {
+        let payload = format!("{}.{}", now() + ACCESS_TTL, random_string(32));
+        let token = format!(
+            "{}.{}",
+            payload,
+            sign(self.signing_key()?.as_bytes(), &payload)
+        );
+        Ok(Session {
+            login,
+            token,
+            refresh_token: random_string(40),
+            expires_in: ACCESS_TTL,
+        })
+    }
+    /// Key access tokens are signed with, generated once (by migration) and kept in database.
+    fn signing_key(&self) -> DBResult<String> {
+        self.get_connection()
+            .query_row(
+                "SELECT value FROM secrets WHERE name = 'session'",
+                params![],
+                |row| row.get(0),
+            )
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test_session {
+    use super::*;
+    use crate::vault::test_db::{auth, vault};
+    use rstest::*;
+
+    #[rstest]
+    fn test_sign() {
+        // RFC 4231, test case 2
+        let signature = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+        assert_eq!(signature, sign(b"Jefe", "what do ya want for nothing?"));
+        assert!(verify(b"Jefe", "what do ya want for nothing?", signature));
+        assert!(!verify(b"Jefe", "what do ya want?", signature));
+        assert!(!verify(b"Jefe", "what do ya want for nothing?", "5bdc"));
+    }
+
+    #[rstest]
+    fn test_session_tokens(vault: &Vault, auth: Option<Authentication>) {
+        let session = vault.login(&auth, None).unwrap();
+        let access = Authentication::from_token(Some(&session.token));
+        assert!(vault.authenticate_user(&access).is_ok());
+
+        // token with forged expiry time is not an access token at all
+        let forged = format!("9{}", session.token);
+        assert!(vault.session_user(&forged).unwrap().is_none());
+
+        let refreshed = vault.refresh_session(&session.refresh_token).unwrap();
+        assert!(matches!(
+            vault.authenticate_user(&access),
+            Err(Unauthenticated)
+        ));
+        assert!(matches!(
+            vault.refresh_session(&session.refresh_token),
+            Err(Unauthenticated)
+        ));
+
+        let access = Authentication::from_token(Some(&refreshed.token));
+        assert!(vault.authenticate_user(&access).is_ok());
+        vault.logout(&access).unwrap();
+        assert!(vault.authenticate_user(&access).is_err());
+        assert!(vault.refresh_session(&refreshed.refresh_token).is_err());
+    }
+
+    #[rstest]
+    fn test_concurrent_refresh(vault: &'static Vault, auth: Option<Authentication>) {
+        let session = vault.login(&auth, None).unwrap();
+        let refreshes: Vec<_> = (0..4)
+            .map(|_| {
+                let token = session.refresh_token.clone();
+                std::thread::spawn(move || vault.refresh_session(&token).is_ok())
+            })
+            .collect();
+        let rotated = refreshes
+            .into_iter()
+            .map(|r| r.join().unwrap())
+            .filter(|is_ok| *is_ok)
+            .count();
+        assert_eq!(1, rotated);
+    }
+}
@@ -1,5 +1,8 @@
+use crate::db::DBError::{BadQuery, DuplicatedQuery, UnknownQuery};
 use crate::db::{DBLookupType, DBResult};
 use crate::vault::auth::Authentication;
+use crate::vault::import::{ConflictPolicy, ImportError, ImportSummary};
+use crate::vault::search;
 use crate::vault::Vault;
 
 use crate::db::query::Query;
@@ -30,12 +33,16 @@ impl From<&Row<'_>> for StoredQuery {
 }
 
 impl Vault {
+    /// Stores a query under given name, replacing the one stored before under the same name.
+    /// Query is validated the same way as when links are searched for.
     pub fn store_query(
         &self,
         auth: &Option<Authentication>,
         name: String,
         query: String,
     ) -> DBResult<i64> {
+        search::parse(&query).map_err(BadQuery)?;
+
         let user = self.authenticate_user(auth)?;
         let mut conn = self.get_connection();
         let txn = conn.transaction().unwrap();
@@ -91,9 +98,11 @@ impl Vault {
             .concat_with_param("user_id = :uid", (":uid", &user.id));
 
         self.get_connection()
-            .query_row(query.build().as_str(), query.named_params(), |r| {
-                Ok(StoredQuery::from(r))
-            })
+            .query_row(
+                query.build().as_str(),
+                query.named_params().as_slice(),
+                |r| Ok(StoredQuery::from(r)),
+            )
             .optional()
             .map_err(Into::into)
     }
@@ -112,4 +121,167 @@ impl Vault {
             Err(e) => Err(e),
         }
     }
+    /// Renames stored query. Returns None if there was no query of given name.
+    pub fn rename_query(
+        &self,
+        auth: &Option<Authentication>,
+        from: &str,
+        to: &str,
+    ) -> DBResult<Option<StoredQuery>> {
+        let user = self.authenticate_user(auth)?;
+        let stored = match self
+            .find_queries(auth, Some(from), DBLookupType::Exact)?
+            .pop()
+        {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+        if from != to
+            && !self
+                .find_queries(auth, Some(to), DBLookupType::Exact)?
+                .is_empty()
+        {
+            return Err(DuplicatedQuery(to.to_string()));
+        }
+        self.get_connection().execute(
+            "UPDATE queries SET name = ?1 WHERE id = ?2 AND user_id = ?3",
+            params![to, stored.id, user.id],
+        )?;
+        Ok(Some(StoredQuery::new(
+            stored.id,
+            to.to_string(),
+            stored.query,
+        )))
+    }
+    /// Stores imported queries, adding them to the summary of imported links. Queries which
+    /// are already stored are left intact with [`ConflictPolicy::Skip`] and replaced otherwise.
+    pub fn import_queries(
+        &self,
+        auth: &Option<Authentication>,
+        queries: Vec<StoredQuery>,
+        policy: ConflictPolicy,
+        dry_run: bool,
+        summary: &mut ImportSummary,
+    ) -> DBResult<()> {
+        for (i, stored) in queries.into_iter().enumerate() {
+            if let Err(e) = search::parse(&stored.query) {
+                let reason = format!("stored query {}: {}", stored.name, e);
+                summary.skip(ImportError::new(i + 1, &reason));
+                continue;
+            }
+            let is_stored = !self
+                .find_queries(auth, Some(&stored.name), DBLookupType::Exact)?
+                .is_empty();
+            if is_stored && policy == ConflictPolicy::Skip {
+                continue;
+            }
+            if !dry_run {
+                self.store_query(auth, stored.name, stored.query)?;
+            }
+            summary.queries += 1;
+        }
+        Ok(())
+    }
+    pub fn expand_query(&self, auth: &Option<Authentication>, query: &str) -> DBResult<String> {
+        expand(query, |name| {
+            self.find_queries(auth, Some(name), DBLookupType::Exact)
+        })
+    }
+}
+
+/// Expands a reference to stored query, like `@rust/async`, into the stored query
+/// followed by the remaining part of given query. Queries not starting with `@`
+/// are returned as they are. Stored queries are looked up by exact name with `find`.
+pub fn expand<F>(query: &str, find: F) -> DBResult<String>
+where
+    F: FnOnce(&str) -> DBResult<Vec<StoredQuery>>,
+{
+    let name = match query.strip_prefix('@') {
+        Some(name) => name,
+        None => return Ok(query.to_string()),
+    };
+    let (name, rest) = name.split_once('/').unwrap_or((name, ""));
+    match find(name)?.first() {
+        Some(stored) => Ok(format!("{} {}", stored.query, rest)),
+        None => Err(UnknownQuery(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test_stored_query {
+    use super::*;
+    use crate::vault::test_db::{auth, vault};
+    use rstest::*;
+
+    fn names(vault: &Vault, auth: &Option<Authentication>) -> Vec<String> {
+        let mut names: Vec<_> = vault
+            .find_queries(auth, Some(""), DBLookupType::Patterned)
+            .unwrap()
+            .into_iter()
+            .map(|q| q.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[rstest]
+    fn test_store_invalid_query(vault: &Vault, auth: Option<Authentication>) {
+        let result = vault.store_query(&auth, "broken".into(), "tags:".into());
+        assert!(matches!(result, Err(BadQuery(_))));
+        assert!(names(vault, &auth).is_empty());
+    }
+
+    #[rstest]
+    fn test_rename_query(vault: &Vault, auth: Option<Authentication>) {
+        vault
+            .store_query(&auth, "go".into(), "tags:go".into())
+            .unwrap();
+        vault
+            .store_query(&auth, "rust".into(), "tags:rust".into())
+            .unwrap();
+
+        let renamed = vault.rename_query(&auth, "go", "golang").unwrap().unwrap();
+        assert_eq!(
+            ("golang", "tags:go"),
+            (renamed.name.as_str(), renamed.query.as_str())
+        );
+        assert_eq!(vec!["golang", "rust"], names(vault, &auth));
+
+        assert!(matches!(
+            vault.rename_query(&auth, "golang", "rust"),
+            Err(DuplicatedQuery(_))
+        ));
+        assert!(vault.rename_query(&auth, "go", "gopher").unwrap().is_none());
+    }
+
+    #[rstest]
+    #[case(ConflictPolicy::Skip, "tags:go")]
+    #[case(ConflictPolicy::Overwrite, "tags:golang")]
+    fn test_import_queries(
+        vault: &Vault,
+        auth: Option<Authentication>,
+        #[case] policy: ConflictPolicy,
+        #[case] expected: &str,
+    ) {
+        vault
+            .store_query(&auth, "go".into(), "tags:go".into())
+            .unwrap();
+
+        let mut summary: ImportSummary = Default::default();
+        let queries = vec![
+            StoredQuery::new(None, "go".into(), "tags:golang".into()),
+            StoredQuery::new(None, "broken".into(), "tags:".into()),
+            StoredQuery::new(None, "rust".into(), "tags:rust".into()),
+        ];
+        vault
+            .import_queries(&auth, queries, policy, false, &mut summary)
+            .unwrap();
+
+        let go = vault
+            .find_queries(&auth, Some("go"), DBLookupType::Exact)
+            .unwrap();
+        assert_eq!(expected, go[0].query);
+        assert_eq!(vec!["go", "rust"], names(vault, &auth));
+        assert_eq!(1, summary.skipped);
+    }
 }
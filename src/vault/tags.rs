@@ -1,14 +1,140 @@
 use crate::db::query::Query;
+use crate::db::DBError::BadAlias;
 use crate::db::DBResult;
-use crate::utils::remove_first;
+use crate::utils::{host, matches_tag, remove_first};
 use crate::vault::auth::Authentication;
+use crate::vault::bulk::BulkResult;
+use crate::vault::link::Version;
+use crate::vault::user::User;
 use crate::vault::Vault;
 
+use miniserde::Serialize;
 use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::rc::Rc;
 
 pub type Tag = String;
 
+/// Tag along with number of (non-deleted) links it's attached to.
+#[derive(Serialize, Clone, Debug)]
+pub struct TagUsage {
+    pub tag: Tag,
+    pub count: u32,
+    pub used_at: String,
+}
+
+impl From<&Row<'_>> for TagUsage {
+    fn from(row: &Row) -> Self {
+        TagUsage {
+            tag: row.get_unwrap(0),
+            count: row.get_unwrap(1),
+            used_at: row.get_unwrap(2),
+        }
+    }
+}
+
+/// Node of tags hierarchy. Tags are split into levels by `/`, so that `lang/rust` and
+/// `lang/go` are both children of `lang` node, no matter if `lang` tag exists or not.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TagNode {
+    pub name: String,
+    pub tag: Tag,
+    /// Number of (non-deleted) links tagged with this tag or any of its descendants
+    pub count: u32,
+    pub children: Vec<TagNode>,
+}
+
+impl TagNode {
+    fn nodes(paths: &BTreeMap<Tag, BTreeSet<i64>>, parent: Option<&str>) -> Vec<TagNode> {
+        paths
+            .iter()
+            .filter(|(tag, _)| tag.rsplit_once('/').map(|(p, _)| p) == parent)
+            .map(|(tag, links)| TagNode {
+                name: tag.rsplit('/').next().unwrap_or_default().to_string(),
+                tag: tag.clone(),
+                count: links.len() as u32,
+                children: TagNode::nodes(paths, Some(tag)),
+            })
+            .collect()
+    }
+}
+
+/// Tag suggested for a link, along with its score.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RelatedTag {
+    pub tag: Tag,
+    pub score: u32,
+}
+
+/// Alternative name of a tag, like `k8s` for `kubernetes`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TagAlias {
+    pub alias: Tag,
+    pub tag: Tag,
+}
+
+impl From<&Row<'_>> for TagAlias {
+    fn from(row: &Row) -> Self {
+        TagAlias {
+            alias: row.get_unwrap(0),
+            tag: row.get_unwrap(1),
+        }
+    }
+}
+
+/// User's tag aliases. Aliases are resolved to their canonical tags when links are stored,
+/// and canonical tags are expanded to their aliases when links are queried, so that links
+/// tagged before an alias was defined are still found.
+#[derive(Default, Debug)]
+pub struct TagAliases {
+    canonical: HashMap<Tag, Tag>,
+    aliases: HashMap<Tag, Vec<Tag>>,
+}
+
+impl TagAliases {
+    pub fn new(aliases: Vec<TagAlias>) -> Self {
+        let mut result = TagAliases::default();
+        for TagAlias { alias, tag } in aliases {
+            result
+                .aliases
+                .entry(tag.clone())
+                .or_default()
+                .push(alias.clone());
+            result.canonical.insert(alias, tag);
+        }
+        result
+    }
+    /// Canonical name of given tag.
+    pub fn canonical<'a>(&'a self, tag: &'a str) -> &'a str {
+        self.canonical.get(tag).map_or(tag, String::as_str)
+    }
+    /// Canonical tag of given one along with all its aliases. Hierarchical tags (like
+    /// `k8s/*`) are expanded with aliases of their parent.
+    pub fn expand(&self, tag: &str) -> Vec<Tag> {
+        let (parent, suffix) = match tag.strip_suffix("/*") {
+            Some(parent) => (self.canonical(parent), "/*"),
+            None => (self.canonical(tag), ""),
+        };
+        let mut expanded = vec![format!("{}{}", parent, suffix)];
+        if let Some(aliases) = self.aliases.get(parent) {
+            expanded.extend(aliases.iter().map(|a| format!("{}{}", a, suffix)));
+        }
+        expanded
+    }
+    /// Classifies tags the same way [Vault::classify_tags] does, with tags expanded to
+    /// their aliases. As link needs only one of aliases of each required tag, required
+    /// tags are returned as groups of alternatives.
+    pub fn classify(&self, tags: Vec<Tag>) -> (Vec<Tag>, Vec<Vec<Tag>>, Vec<Tag>) {
+        let (optional, required, excluded) = Vault::classify_tags(tags);
+        (
+            optional.iter().flat_map(|t| self.expand(t)).collect(),
+            required.iter().map(|t| self.expand(t)).collect(),
+            excluded.iter().flat_map(|t| self.expand(t)).collect(),
+        )
+    }
+}
+
 impl Vault {
     pub fn classify_tags(tags: Vec<Tag>) -> (Vec<Tag>, Vec<Tag>, Vec<Tag>) {
         let mut optional = Vec::new();
@@ -48,11 +174,635 @@ impl Vault {
         );
         let limit = limit.unwrap_or(8);
 
+        // aliases are never suggested, their canonical tags are.
+
         Query::new_with_initial("SELECT tag FROM tags")
             .concat_with_param("WHERE user_id = :id AND", (":id", &user.id))
+            .concat("tag NOT IN (SELECT alias FROM tag_aliases WHERE user_id = :id) AND")
             .concat_with_param("tag LIKE :pattern AND", (":pattern", &pattern))
             .concat_with_param("tag NOT IN rarray(:excludes)", (":excludes", &excludes))
             .concat_with_param("ORDER BY used_at DESC LIMIT :limit", (":limit", &limit))
             .fetch_as(self.get_connection(), |row| row.get_unwrap::<_, Tag>(0))
     }
+    pub fn tags_usage(
+        &self,
+        auth: &Option<Authentication>,
+        pattern: Option<&str>,
+    ) -> DBResult<Vec<TagUsage>> {
+        let user = self.authenticate_user(auth)?;
+        let pattern = Query::patternize(pattern.unwrap_or_default());
+
+        Query::new_with_initial(
+            "SELECT t.tag, count(l.id), datetime(t.used_at) FROM tags t \
+             LEFT JOIN links_tags lt ON lt.tag_id = t.id \
+             LEFT JOIN links l ON l.id = lt.link_id AND l.deleted_at IS NULL",
+        )
+        .concat_with_param("WHERE t.user_id = :id AND", (":id", &user.id))
+        .concat_with_param("t.tag LIKE :pattern", (":pattern", &pattern))
+        .concat("GROUP BY t.id ORDER BY count(l.id) DESC, t.tag")
+        .fetch(self.get_connection())
+    }
+    /// Returns hierarchy of tags matching given pattern, along with ancestors of matching tags.
+    pub fn tags_tree(
+        &self,
+        auth: &Option<Authentication>,
+        pattern: Option<&str>,
+    ) -> DBResult<Vec<TagNode>> {
+        let user = self.authenticate_user(auth)?;
+        let pattern = Query::patternize(pattern.unwrap_or_default());
+        let tagged = Query::new_with_initial(
+            "SELECT t.tag, l.id FROM tags t \
+             LEFT JOIN links_tags lt ON lt.tag_id = t.id \
+             LEFT JOIN links l ON l.id = lt.link_id AND l.deleted_at IS NULL",
+        )
+        .concat_with_param("WHERE t.user_id = :id AND", (":id", &user.id))
+        .concat_with_param("t.tag LIKE :pattern", (":pattern", &pattern))
+        .fetch_as(self.get_connection(), |row| {
+            (
+                row.get_unwrap::<_, Tag>(0),
+                row.get_unwrap::<_, Option<i64>>(1),
+            )
+        })?;
+
+        // links are collected for each level of the tag, so that link tagged with
+        // both lang/rust and lang/go is counted once within lang node.
+
+        let mut paths: BTreeMap<Tag, BTreeSet<i64>> = BTreeMap::new();
+        for (tag, link) in tagged {
+            let mut end = 0;
+            for segment in tag.split('/') {
+                end += segment.len();
+                let links = paths.entry(tag[..end].to_string()).or_default();
+                links.extend(link);
+                end += 1;
+            }
+        }
+        Ok(TagNode::nodes(&paths, None))
+    }
+    /// Renames a tag. Renaming to a tag which already exists merges both tags.
+    pub fn rename_tag(
+        &self,
+        auth: &Option<Authentication>,
+        from: &str,
+        to: &str,
+    ) -> DBResult<BulkResult> {
+        self.merge_tags(auth, &[from], to)
+    }
+    /// Merges tags into given one, which is created if it doesn't exist yet. Links tagged
+    /// with any of merged tags get a new version.
+    pub fn merge_tags(
+        &self,
+        auth: &Option<Authentication>,
+        from: &[&str],
+        into: &str,
+    ) -> DBResult<BulkResult> {
+        let user = self.authenticate_user(auth)?;
+        let merged = tag_values(from.iter().filter(|t| **t != into));
+        let mut conn = self.get_connection();
+        let txn = conn.transaction()?;
+        let latest = self.latest_version_in(&user, &txn)?;
+
+        let existing: u32 = txn.query_row(
+            "SELECT count(*) FROM tags WHERE user_id = ?1 AND tag IN rarray(?2)",
+            params![user.id, merged],
+            |row| row.get(0),
+        )?;
+        if existing == 0 {
+            return Ok(BulkResult::new(latest, Vec::new()));
+        }
+        let ids = tagged_links(&txn, &user, &merged)?;
+
+        // single tag renamed to a new one keeps its identity. otherwise links get
+        // connected with target tag and merged tags are removed.
+        let renamed = txn.execute(
+            "UPDATE tags SET tag = ?1, used_at = CURRENT_TIMESTAMP \
+             WHERE user_id = ?2 AND tag IN rarray(?3) AND ?4 = 1 \
+             AND NOT EXISTS (SELECT 1 FROM tags WHERE user_id = ?2 AND tag = ?1)",
+            params![into, user.id, merged, existing],
+        )?;
+        if renamed == 0 {
+            txn.execute(
+                "INSERT INTO tags(tag, user_id) VALUES(?1, ?2) \
+                 ON CONFLICT(tag, user_id) DO UPDATE SET used_at = CURRENT_TIMESTAMP",
+                params![into, user.id],
+            )?;
+            txn.execute(
+                "INSERT OR IGNORE INTO links_tags(link_id, tag_id) \
+                 SELECT lt.link_id, (SELECT id FROM tags WHERE user_id = ?1 AND tag = ?2) \
+                 FROM links_tags lt JOIN tags t ON t.id = lt.tag_id \
+                 WHERE t.user_id = ?1 AND t.tag IN rarray(?3)",
+                params![user.id, into, merged],
+            )?;
+            remove_tags(&txn, &user, &merged)?;
+        }
+        self.commit_tagged(txn, ids, latest)
+    }
+    /// Removes tags from all the links they're attached to.
+    pub fn delete_tags(
+        &self,
+        auth: &Option<Authentication>,
+        tags: &[&str],
+    ) -> DBResult<BulkResult> {
+        let user = self.authenticate_user(auth)?;
+        let tags = tag_values(tags.iter());
+        let mut conn = self.get_connection();
+        let txn = conn.transaction()?;
+        let latest = self.latest_version_in(&user, &txn)?;
+
+        let ids = tagged_links(&txn, &user, &tags)?;
+        remove_tags(&txn, &user, &tags)?;
+        self.commit_tagged(txn, ids, latest)
+    }
+    /// Removes tags which are not attached to any link. Returns number of removed tags.
+    pub fn prune_tags(&self, auth: &Option<Authentication>) -> DBResult<usize> {
+        let user = self.authenticate_user(auth)?;
+        self.get_connection()
+            .execute(
+                "DELETE FROM tags WHERE user_id = ?1 AND id NOT IN (SELECT tag_id FROM links_tags)",
+                params![user.id],
+            )
+            .map_err(Into::into)
+    }
+    /// Suggests tags for a link already tagged with given ones. Tags are scored by number of
+    /// user's links they were attached to along with any of given tags, and by number of links
+    /// from the same host (if link's url or domain is known) they were attached to.
+    pub fn related_tags(
+        &self,
+        auth: &Option<Authentication>,
+        tags: &[Tag],
+        href: Option<&str>,
+        limit: Option<u16>,
+    ) -> DBResult<Vec<RelatedTag>> {
+        let user = self.authenticate_user(auth)?;
+        let aliases = self.tag_aliases(&user, &self.get_connection())?;
+        let chosen: Vec<Tag> = tags
+            .iter()
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .flat_map(|t| aliases.expand(t))
+            .collect();
+        let limit = limit.unwrap_or(8) as usize;
+        let mut scored: Vec<(Tag, u32)> = Vec::new();
+
+        if !chosen.is_empty() {
+            let values = Rc::new(
+                chosen
+                    .iter()
+                    .cloned()
+                    .map(SqlValue::from)
+                    .collect::<Vec<_>>(),
+            );
+            scored.extend(
+                Query::new_with_initial(
+                    "SELECT t.tag, count(*) FROM links l \
+                     JOIN links_tags lt ON lt.link_id = l.id JOIN tags t ON t.id = lt.tag_id",
+                )
+                .concat_with_param(
+                    "WHERE l.user_id = :id AND l.deleted_at IS NULL",
+                    (":id", &user.id),
+                )
+                .concat_with_param(
+                    "AND l.id IN (\
+                     SELECT lt.link_id FROM links_tags lt JOIN tags t ON t.id = lt.tag_id \
+                     WHERE t.user_id = :id AND t.tag IN rarray(:chosen)) GROUP BY t.id",
+                    (":chosen", &values),
+                )
+                .fetch_as(self.get_connection(), |row| {
+                    (row.get_unwrap::<_, Tag>(0), row.get_unwrap::<_, u32>(1))
+                })?,
+            );
+        }
+        if let Some(host) = href.map(host).filter(|h| !h.is_empty()) {
+            scored.extend(
+                Query::new_with_initial(
+                    "SELECT t.tag, count(*) FROM links l \
+                     JOIN links_tags lt ON lt.link_id = l.id JOIN tags t ON t.id = lt.tag_id",
+                )
+                .concat_with_param("WHERE l.user_id = :id", (":id", &user.id))
+                .concat_with_param(
+                    "AND l.deleted_at IS NULL AND host(l.href) = :host GROUP BY t.id",
+                    (":host", &host),
+                )
+                .fetch_as(self.get_connection(), |row| {
+                    (row.get_unwrap::<_, Tag>(0), row.get_unwrap::<_, u32>(1))
+                })?,
+            );
+        }
+
+        // scores of aliases add up to scores of their canonical tags. tags already
+        // chosen are not suggested again.
+
+        let mut scores: HashMap<&str, u32> = HashMap::new();
+        for (tag, score) in &scored {
+            let tag = aliases.canonical(tag);
+            if !chosen.iter().any(|c| matches_tag(tag, c)) {
+                *scores.entry(tag).or_default() += score;
+            }
+        }
+        let mut related: Vec<_> = scores
+            .into_iter()
+            .map(|(tag, score)| RelatedTag {
+                tag: tag.to_string(),
+                score,
+            })
+            .collect();
+        related.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.tag.cmp(&b.tag)));
+        related.truncate(limit);
+        Ok(related)
+    }
+    /// Loads all the tag aliases defined by user.
+    pub fn tag_aliases(&self, user: &User, conn: &Connection) -> DBResult<TagAliases> {
+        let mut stmt = conn.prepare("SELECT alias, tag FROM tag_aliases WHERE user_id = ?1")?;
+        let aliases = stmt.query_map(params![user.id], |row| Ok(TagAlias::from(row)))?;
+        Ok(TagAliases::new(aliases.collect::<Result<_, _>>()?))
+    }
+    pub fn find_aliases(
+        &self,
+        auth: &Option<Authentication>,
+        tag: Option<&str>,
+    ) -> DBResult<Vec<TagAlias>> {
+        let user = self.authenticate_user(auth)?;
+        let pattern = Query::patternize(tag.unwrap_or_default());
+
+        Query::new_with_initial("SELECT alias, tag FROM tag_aliases")
+            .concat_with_param("WHERE user_id = :id AND", (":id", &user.id))
+            .concat_with_param(
+                "(tag LIKE :pattern OR alias LIKE :pattern)",
+                (":pattern", &pattern),
+            )
+            .concat("ORDER BY tag, alias")
+            .fetch(self.get_connection())
+    }
+    /// Defines an alias of a tag. Aliases can't be chained, so neither tag can be an alias
+    /// itself nor alias can have aliases of its own.
+    pub fn add_alias(
+        &self,
+        auth: &Option<Authentication>,
+        alias: &str,
+        tag: &str,
+    ) -> DBResult<TagAlias> {
+        let user = self.authenticate_user(auth)?;
+        let (alias, tag) = (alias.trim(), tag.trim());
+        if alias.is_empty() || tag.is_empty() || alias == tag {
+            return Err(BadAlias(format!("{} -> {}", alias, tag)));
+        }
+        let conn = self.get_connection();
+        let aliases = self.tag_aliases(&user, &conn)?;
+        if aliases.canonical(tag) != tag {
+            return Err(BadAlias(format!(
+                "{} is an alias of {}",
+                tag,
+                aliases.canonical(tag)
+            )));
+        }
+        if aliases.expand(alias).len() > 1 && aliases.canonical(alias) == alias {
+            return Err(BadAlias(format!("{} has aliases of its own", alias)));
+        }
+        conn.execute(
+            "INSERT INTO tag_aliases(user_id, alias, tag) VALUES(?1, ?2, ?3) \
+             ON CONFLICT(user_id, alias) DO UPDATE SET tag = ?3",
+            params![user.id, alias, tag],
+        )?;
+        Ok(TagAlias {
+            alias: alias.to_string(),
+            tag: tag.to_string(),
+        })
+    }
+    /// Removes an alias. Returns the tag alias pointed to, if alias was defined.
+    pub fn del_alias(&self, auth: &Option<Authentication>, alias: &str) -> DBResult<Option<Tag>> {
+        let user = self.authenticate_user(auth)?;
+        let conn = self.get_connection();
+        let tag = conn
+            .query_row(
+                "SELECT tag FROM tag_aliases WHERE user_id = ?1 AND alias = ?2",
+                params![user.id, alias],
+                |row| row.get(0),
+            )
+            .optional()?;
+        conn.execute(
+            "DELETE FROM tag_aliases WHERE user_id = ?1 AND alias = ?2",
+            params![user.id, alias],
+        )?;
+        Ok(tag)
+    }
+    /// Refreshes links which had their tags changed and commits the changes. All the links
+    /// get a single new version.
+    fn commit_tagged(
+        &self,
+        txn: Transaction,
+        ids: Vec<i64>,
+        latest: Version,
+    ) -> DBResult<BulkResult> {
+        let version = if ids.is_empty() {
+            latest
+        } else {
+            latest.bump()
+        };
+        self.refresh_links(&ids, &version, &txn)?;
+        txn.commit()?;
+        Ok(BulkResult::new(version, ids))
+    }
+}
+
+fn tag_values<'a, I: Iterator<Item = &'a &'a str>>(tags: I) -> Rc<Vec<SqlValue>> {
+    Rc::new(tags.map(|t| SqlValue::from(t.to_string())).collect())
+}
+
+/// Ids of links tagged with any of given tags.
+fn tagged_links(txn: &Transaction, user: &User, tags: &Rc<Vec<SqlValue>>) -> DBResult<Vec<i64>> {
+    let mut stmt = txn.prepare(
+        "SELECT DISTINCT lt.link_id FROM links_tags lt JOIN tags t ON t.id = lt.tag_id \
+         WHERE t.user_id = ?1 AND t.tag IN rarray(?2) ORDER BY lt.link_id",
+    )?;
+    let ids = stmt.query_map(params![user.id, tags], |row| row.get(0))?;
+    ids.collect::<Result<_, _>>().map_err(Into::into)
+}
+
+fn remove_tags(txn: &Transaction, user: &User, tags: &Rc<Vec<SqlValue>>) -> DBResult<()> {
+    txn.execute(
+        "DELETE FROM links_tags WHERE tag_id IN (SELECT id FROM tags WHERE user_id = ?1 AND tag IN rarray(?2))",
+        params![user.id, tags],
+    )?;
+    txn.execute(
+        "DELETE FROM tags WHERE user_id = ?1 AND tag IN rarray(?2)",
+        params![user.id, tags],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_tags {
+    #![allow(unused_must_use)]
+
+    use super::*;
+    use crate::vault::test_db::{add_tagged, auth, names, tags_of, vault};
+    use rstest::*;
+
+    #[rstest]
+    fn test_tags_usage(vault: &Vault, auth: Option<Authentication>) {
+        add_tagged(vault, &auth, "http://usage.one", &["rust", "async"]);
+        add_tagged(vault, &auth, "http://usage.two", &["rust"]);
+        add_tagged(vault, &auth, "http://usage.three", &["async", "old"]);
+        vault.del_link(&auth, "http://usage.three");
+
+        let usage: Vec<_> = vault
+            .tags_usage(&auth, None)
+            .unwrap()
+            .into_iter()
+            .map(|u| (u.tag, u.count))
+            .collect();
+        assert_eq!(
+            vec![
+                ("rust".to_string(), 2),
+                ("async".to_string(), 1),
+                ("old".to_string(), 0)
+            ],
+            usage
+        );
+        assert_eq!(1, vault.tags_usage(&auth, Some("ru")).unwrap().len());
+    }
+
+    #[rstest]
+    fn test_rename_tag(vault: &Vault, auth: Option<Authentication>) {
+        let link = add_tagged(vault, &auth, "http://rename.tag", &["rust", "lang"]);
+        let result = vault.rename_tag(&auth, "lang", "language").unwrap();
+        let renamed = vault.get_link(&auth, "http://rename.tag").unwrap().unwrap();
+
+        assert_eq!(vec![link.id.unwrap()], result.ids);
+        assert_eq!(
+            Some(vec!["language".to_string(), "rust".to_string()]),
+            renamed.tags
+        );
+        assert_ne!(link.hash, renamed.hash);
+        assert_eq!(renamed.hash, renamed.clone().digest().hash);
+
+        let (_, version) = vault
+            .query_links(&auth, "", Version::new(result.version - 1), None)
+            .unwrap();
+        assert_eq!(result.version, version.offset());
+
+        // renaming to the same tag or a missing one changes nothing
+        assert!(vault
+            .rename_tag(&auth, "rust", "rust")
+            .unwrap()
+            .ids
+            .is_empty());
+        assert!(vault
+            .rename_tag(&auth, "missing", "rust")
+            .unwrap()
+            .ids
+            .is_empty());
+    }
+
+    #[rstest]
+    fn test_merge_tags(vault: &Vault, auth: Option<Authentication>) {
+        add_tagged(vault, &auth, "http://merge.one", &["js", "web"]);
+        add_tagged(vault, &auth, "http://merge.two", &["javascript"]);
+        add_tagged(
+            vault,
+            &auth,
+            "http://merge.three",
+            &["ecmascript", "javascript"],
+        );
+
+        let result = vault
+            .merge_tags(&auth, &["js", "ecmascript"], "javascript")
+            .unwrap();
+
+        assert_eq!(2, result.ids.len());
+        assert_eq!(
+            vec!["javascript", "web"],
+            tags_of(vault, &auth, "http://merge.one")
+        );
+        assert_eq!(
+            vec!["javascript"],
+            tags_of(vault, &auth, "http://merge.three")
+        );
+
+        let tags: Vec<_> = vault
+            .tags_usage(&auth, None)
+            .unwrap()
+            .into_iter()
+            .map(|u| (u.tag, u.count))
+            .collect();
+        assert_eq!(
+            vec![("javascript".to_string(), 3), ("web".to_string(), 1)],
+            tags
+        );
+    }
+
+    #[rstest]
+    fn test_delete_and_prune_tags(vault: &Vault, auth: Option<Authentication>) {
+        add_tagged(vault, &auth, "http://delete.tag", &["keep", "drop"]);
+        add_tagged(vault, &auth, "http://prune.tag", &["orphan"]);
+        add_tagged(vault, &auth, "http://prune.tag", &["keep"]);
+
+        let result = vault.delete_tags(&auth, &["drop"]).unwrap();
+        assert_eq!(1, result.ids.len());
+        assert_eq!(vec!["keep"], tags_of(vault, &auth, "http://delete.tag"));
+
+        assert_eq!(1, vault.prune_tags(&auth).unwrap());
+        let tags: Vec<_> = vault
+            .tags_usage(&auth, None)
+            .unwrap()
+            .into_iter()
+            .map(|u| u.tag)
+            .collect();
+        assert_eq!(vec!["keep"], tags);
+    }
+
+    #[rstest]
+    fn test_tags_tree(vault: &Vault, auth: Option<Authentication>) {
+        add_tagged(vault, &auth, "http://tree.one", &["lang/rust", "lang/go"]);
+        add_tagged(vault, &auth, "http://tree.two", &["lang/rust/async"]);
+        add_tagged(
+            vault,
+            &auth,
+            "http://tree.three",
+            &["lang", "work/infra/k8s"],
+        );
+
+        let tree = vault.tags_tree(&auth, None).unwrap();
+        let flatten = |nodes: &[TagNode]| -> Vec<(String, u32)> {
+            nodes.iter().map(|n| (n.tag.clone(), n.count)).collect()
+        };
+
+        assert_eq!(
+            vec![("lang".to_string(), 3), ("work".to_string(), 1)],
+            flatten(&tree)
+        );
+        assert_eq!(
+            vec![("lang/go".to_string(), 1), ("lang/rust".to_string(), 2)],
+            flatten(&tree[0].children)
+        );
+        assert_eq!("async", tree[0].children[1].children[0].name);
+        assert_eq!("k8s", tree[1].children[0].children[0].name);
+
+        // ancestors of matching tags are kept
+        let tree = vault.tags_tree(&auth, Some("k8s")).unwrap();
+        assert_eq!("work/infra/k8s", tree[0].children[0].children[0].tag);
+    }
+
+    #[rstest]
+    fn test_classify_with_aliases() {
+        let aliases = TagAliases::new(vec![
+            TagAlias {
+                alias: "k8s".into(),
+                tag: "kubernetes".into(),
+            },
+            TagAlias {
+                alias: "kube".into(),
+                tag: "kubernetes".into(),
+            },
+        ]);
+        let (optional, required, excluded) = aliases.classify(vec![
+            "rust".into(),
+            "+kubernetes".into(),
+            "-kubernetes/*".into(),
+        ]);
+
+        assert_eq!(vec!["rust"], optional);
+        assert_eq!(vec![vec!["kubernetes", "k8s", "kube"]], required);
+        assert_eq!(vec!["kubernetes/*", "k8s/*", "kube/*"], excluded);
+        assert_eq!(vec!["kubernetes", "k8s", "kube"], aliases.expand("kube"));
+        assert_eq!("kubernetes", aliases.canonical("k8s"));
+        assert_eq!("rust", aliases.canonical("rust"));
+    }
+
+    #[rstest]
+    fn test_tag_aliases(vault: &Vault, auth: Option<Authentication>) {
+        add_tagged(vault, &auth, "http://before.alias", &["k8s", "infra"]);
+        vault.add_alias(&auth, "k8s", "kubernetes").unwrap();
+        add_tagged(vault, &auth, "http://after.alias", &["k8s", "kubernetes"]);
+
+        // aliases are stored as canonical tags
+        assert_eq!(
+            vec!["kubernetes"],
+            tags_of(vault, &auth, "http://after.alias")
+        );
+
+        assert_eq!(
+            vec!["http://after.alias", "http://before.alias"],
+            names(vault, &auth, "tags:kubernetes")
+        );
+        assert_eq!(
+            vec!["http://before.alias"],
+            names(vault, &auth, "tags:+kubernetes,+infra")
+        );
+        assert!(names(vault, &auth, "tags:-kubernetes").is_empty());
+
+        // only canonical tags are suggested
+        let recent = vault.recent_tags(&auth, None, None, None).unwrap();
+        assert!(recent.contains(&"kubernetes".to_string()));
+        assert!(!recent.contains(&"k8s".to_string()));
+
+        // aliases can't be chained
+        assert!(matches!(
+            vault.add_alias(&auth, "kube", "k8s"),
+            Err(BadAlias(_))
+        ));
+        assert!(matches!(
+            vault.add_alias(&auth, "kubernetes", "orchestration"),
+            Err(BadAlias(_))
+        ));
+
+        assert_eq!(
+            Some("kubernetes".to_string()),
+            vault.del_alias(&auth, "k8s").unwrap()
+        );
+        assert!(vault.find_aliases(&auth, None).unwrap().is_empty());
+        assert_eq!(
+            vec!["http://after.alias"],
+            names(vault, &auth, "tags:kubernetes")
+        );
+    }
+
+    #[rstest]
+    fn test_related_tags(vault: &Vault, auth: Option<Authentication>) {
+        add_tagged(vault, &auth, "http://tokio.rs", &["rust", "async"]);
+        add_tagged(
+            vault,
+            &auth,
+            "http://docs.rs/hyper",
+            &["rust", "async", "http"],
+        );
+        add_tagged(vault, &auth, "http://docs.rs/serde", &["rust", "serde"]);
+        add_tagged(vault, &auth, "http://golang.org", &["go", "async"]);
+
+        let related = |tags: &[&str], href: Option<&str>| -> Vec<(String, u32)> {
+            let tags: Vec<Tag> = tags.iter().map(|t| t.to_string()).collect();
+            vault
+                .related_tags(&auth, &tags, href, None)
+                .unwrap()
+                .into_iter()
+                .map(|t| (t.tag, t.score))
+                .collect()
+        };
+
+        assert_eq!(
+            vec![
+                ("async".to_string(), 2),
+                ("http".to_string(), 1),
+                ("serde".to_string(), 1)
+            ],
+            related(&["rust"], None)
+        );
+        assert_eq!(
+            vec![
+                ("rust".to_string(), 2),
+                ("async".to_string(), 1),
+                ("http".to_string(), 1),
+                ("serde".to_string(), 1)
+            ],
+            related(&[], Some("https://www.docs.rs/tokio"))
+        );
+        assert_eq!(
+            vec![
+                ("async".to_string(), 3),
+                ("http".to_string(), 2),
+                ("serde".to_string(), 2)
+            ],
+            related(&["rust"], Some("docs.rs"))
+        );
+    }
 }
@@ -0,0 +1,267 @@
+use crate::db::query::Query;
+use crate::db::DBError::{BadTimestamp, DuplicatedToken, Forbidden, NotAdmin, UnknownUser};
+use crate::db::DBResult;
+use crate::utils::{normalize_datetime, random_string};
+use crate::vault::auth::Authentication;
+use crate::vault::user::User;
+use crate::vault::Vault;
+
+use miniserde::Serialize;
+use rusqlite::{params, OptionalExtension, Row};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+/// What API token is allowed to do. Each scope includes the ones before it.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Scope {
+    /// Only reading links, tags and stored queries
+    Read,
+    /// Reading and changing user's data
+    Write,
+    /// Everything, administration included
+    Admin,
+}
+
+impl FromStr for Scope {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "read" | "read-only" => Ok(Scope::Read),
+            "write" => Ok(Scope::Write),
+            "admin" => Ok(Scope::Admin),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scope = match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        };
+        write!(f, "{}", scope)
+    }
+}
+
+/// Named API token. Token itself is never stored, only its hash is.
+#[derive(Serialize, Clone, Debug)]
+pub struct Token {
+    pub name: String,
+    pub scope: String,
+    pub created_at: String,
+    pub used_at: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+impl From<&Row<'_>> for Token {
+    fn from(row: &Row) -> Self {
+        Token {
+            name: row.get_unwrap(0),
+            scope: row.get_unwrap(1),
+            created_at: row.get_unwrap(2),
+            used_at: row.get_unwrap(3),
+            expires_at: row.get_unwrap(4),
+        }
+    }
+}
+
+pub fn digest(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+impl Vault {
+    /// Generates a new named token of given scope. Returned token is shown only once,
+    /// as there is no way to get it back from its hash.
+    pub fn add_token(
+        &self,
+        login: &str,
+        name: &str,
+        scope: Scope,
+        expires_at: Option<&str>,
+    ) -> DBResult<(Token, String)> {
+        let (user, _) = self.find_user(login)?;
+        let expires_at = match expires_at {
+            Some(e) => Some(normalize_datetime(e).ok_or_else(|| BadTimestamp(e.to_string()))?),
+            None => None,
+        };
+        if self.get_token(&user, name)?.is_some() {
+            return Err(DuplicatedToken(name.to_string()));
+        }
+        let token = random_string(40);
+        self.get_connection().execute(
+            "INSERT INTO tokens(user_id, name, hash, scope, expires_at) VALUES(?1, ?2, ?3, ?4, ?5)",
+            params![user.id, name, digest(&token), scope.to_string(), expires_at],
+        )?;
+        let stored = self.get_token(&user, name)?.ok_or(UnknownUser)?;
+        Ok((stored, token))
+    }
+    pub fn find_tokens(&self, login: &str) -> DBResult<Vec<Token>> {
+        let (user, _) = self.find_user(login)?;
+        Query::new_with_initial(
+            "SELECT name, scope, created_at, used_at, expires_at FROM tokens WHERE",
+        )
+        .concat_with_param("user_id = :id ORDER BY created_at DESC", (":id", &user.id))
+        .fetch(self.get_connection())
+    }
+    /// Revokes token of given name. Returns None if there was no such a token.
+    pub fn revoke_token(&self, login: &str, name: &str) -> DBResult<Option<Token>> {
+        let (user, _) = self.find_user(login)?;
+        match self.get_token(&user, name)? {
+            Some(token) => {
+                self.get_connection().execute(
+                    "DELETE FROM tokens WHERE user_id = ?1 AND name = ?2",
+                    params![user.id, name],
+                )?;
+                Ok(Some(token))
+            }
+            None => Ok(None),
+        }
+    }
+    /// Authenticates user and makes sure that used token has at least given scope.
+    pub fn authorize(&self, auth: &Option<Authentication>, scope: Scope) -> DBResult<User> {
+        match auth {
+            Some(Authentication::Token(token)) => {
+                let (user, granted) = self.token_user(token.as_str())?;
+                if granted >= scope {
+                    Ok(user)
+                } else {
+                    Err(Forbidden)
+                }
+            }
+            _ => self.authenticate_user(auth),
+        }
+    }
+    /// Authenticates user and makes sure they have admin role (and token has admin scope).
+    pub fn authorize_admin(&self, auth: &Option<Authentication>) -> DBResult<User> {
+        let user = self.authorize(auth, Scope::Admin)?;
+        if self.is_admin(&user)? {
+            Ok(user)
+        } else {
+            Err(NotAdmin)
+        }
+    }
+    /// Looks up owner of a token, along with token's scope. Tokens are checked against their
+    /// expiry time and have their last usage recorded. Session access tokens are accepted too,
+    /// with admin scope granted to admins only.
+    pub fn token_user(&self, token: &str) -> DBResult<(User, Scope)> {
+        if let Some(user) = self.session_user(token)? {
+            let scope = if self.is_admin(&user)? {
+                Scope::Admin
+            } else {
+                Scope::Write
+            };
+            return Ok((user, scope));
+        }
+        let conn = self.get_connection();
+        let hash = digest(token);
+        let found: Option<(i64, String, String)> = conn
+            .query_row(
+                "SELECT u.id, u.login, t.scope FROM tokens t INNER JOIN users u ON t.user_id = u.id \
+                WHERE t.hash = ?1 AND (t.expires_at IS NULL OR t.expires_at > CURRENT_TIMESTAMP) \
+                AND NOT u.is_disabled",
+                params![hash],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        match found {
+            Some((id, login, scope)) => {
+                conn.execute(
+                    "UPDATE tokens SET used_at = CURRENT_TIMESTAMP WHERE hash = ?1",
+                    params![hash],
+                )?;
+                Ok((User::new(id, &login), scope.parse().unwrap_or(Scope::Read)))
+            }
+            None => Err(UnknownUser),
+        }
+    }
+    fn get_token(&self, user: &User, name: &str) -> DBResult<Option<Token>> {
+        self.get_connection()
+            .query_row(
+                "SELECT name, scope, created_at, used_at, expires_at FROM tokens \
+                WHERE user_id = ?1 AND name = ?2",
+                params![user.id, name],
+                |row| Ok(Token::from(row)),
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test_token {
+    use super::*;
+    use crate::vault::test_db::{auth, vault};
+    use rstest::*;
+
+    fn login(auth: &Option<Authentication>) -> String {
+        match auth {
+            Some(Authentication::Credentials(login, _)) => login.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[rstest]
+    fn test_add_token(vault: &Vault, auth: Option<Authentication>) {
+        let login = login(&auth);
+        let (token, secret) = vault.add_token(&login, "ci", Scope::Read, None).unwrap();
+        assert_eq!(("ci", "read"), (token.name.as_str(), token.scope.as_str()));
+
+        let auth = Authentication::from_token(Some(&secret));
+        assert_eq!(
+            login.to_lowercase(),
+            vault.authenticate_user(&auth).unwrap().login
+        );
+        assert!(vault.authorize(&auth, Scope::Read).is_ok());
+        assert!(matches!(
+            vault.authorize(&auth, Scope::Write),
+            Err(Forbidden)
+        ));
+
+        let tokens = vault.find_tokens(&login).unwrap();
+        assert_eq!(1, tokens.len());
+        assert!(tokens[0].used_at.is_some());
+        assert!(matches!(
+            vault.add_token(&login, "ci", Scope::Write, None),
+            Err(DuplicatedToken(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_sql_digest(vault: &Vault) {
+        // legacy API keys are hashed by migration with SQL function
+        let hashed: String = vault
+            .get_connection()
+            .query_row("SELECT sha256('legacy-key')", params![], |row| row.get(0))
+            .unwrap();
+        assert_eq!(digest("legacy-key"), hashed);
+    }
+
+    #[rstest]
+    fn test_expired_token(vault: &Vault, auth: Option<Authentication>) {
+        let login = login(&auth);
+        let (_, secret) = vault
+            .add_token(&login, "old", Scope::Write, Some("2001-01-01"))
+            .unwrap();
+        let auth = Authentication::from_token(Some(&secret));
+        assert!(matches!(vault.authenticate_user(&auth), Err(UnknownUser)));
+    }
+
+    #[rstest]
+    fn test_revoke_token(vault: &Vault, auth: Option<Authentication>) {
+        let login = login(&auth);
+        let (_, secret) = vault
+            .add_token(&login, "laptop", Scope::Admin, None)
+            .unwrap();
+
+        assert!(vault.revoke_token(&login, "laptop").unwrap().is_some());
+        assert!(vault.revoke_token(&login, "laptop").unwrap().is_none());
+        assert!(vault
+            .authenticate_user(&Authentication::from_token(Some(&secret)))
+            .is_err());
+    }
+}
@@ -1,12 +1,13 @@
-use crate::db::DBError::UnknownUser;
+use crate::db::DBError::{BadImport, DuplicatedUser, UnknownUser};
 use crate::db::DBLookupType::{Exact, Patterned};
 use crate::db::{DBLookupType, DBResult};
-use crate::utils::{confirm, password, random_string};
 use crate::vault::Vault;
 
 use crate::db::query::Query;
 use bcrypt::hash;
-use rusqlite::params;
+use miniserde::Serialize;
+use rusqlite::Error::SqliteFailure;
+use rusqlite::{params, ErrorCode};
 use std::fmt;
 
 #[derive(Clone, Debug)]
@@ -15,6 +16,15 @@ pub struct User {
     pub login: String,
 }
 
+/// User along with their role and number of owned links.
+#[derive(Serialize, Clone, Debug)]
+pub struct Account {
+    pub login: String,
+    pub links: u32,
+    pub admin: bool,
+    pub disabled: bool,
+}
+
 impl fmt::Display for User {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.login)
@@ -31,20 +41,27 @@ impl User {
 }
 
 impl Vault {
-    /// Does a user look up by name, depending on [lookup type] either exactly as provided or as a substring.
+    /// Does a user look up by name, depending on [lookup type] either exactly as provided (ignoring
+    /// case) or as a substring.
     ///
     /// [lookup type]: DBLookupType
     fn find_users(&self, pattern: &str, lookup_type: DBLookupType) -> DBResult<Vec<(User, u32)>> {
-        let login = match lookup_type {
-            Exact => pattern.to_owned(),
-            Patterned => Query::patternize(pattern),
+        let (condition, login) = match lookup_type {
+            Exact => (
+                "WHERE login = :login COLLATE NOCASE GROUP BY login",
+                pattern.to_owned(),
+            ),
+            Patterned => (
+                "WHERE login LIKE :login GROUP BY login",
+                Query::patternize(pattern),
+            ),
         };
 
         Query::new_with_initial(
             "SELECT u.id, login, count(l.id) FROM users u \
             LEFT JOIN links l ON l.user_id = u.id",
         )
-        .concat_with_param("WHERE login LIKE :login GROUP BY login", (":login", &login))
+        .concat_with_param(condition, (":login", &login))
         .fetch_as(self.get_connection(), |row| {
             (
                 User {
@@ -61,67 +78,248 @@ impl Vault {
             .first()
             .map_or(Err(UnknownUser), |(user, count)| Ok((user.clone(), *count)))
     }
-    pub fn match_users(&self, pattern: &str) -> DBResult<Vec<(User, u32)>> {
-        self.find_users(pattern, DBLookupType::Patterned)
+    pub fn match_users(&self, pattern: &str) -> DBResult<Vec<Account>> {
+        Query::new_with_initial(
+            "SELECT login, count(l.id), is_admin, is_disabled FROM users u \
+            LEFT JOIN links l ON l.user_id = u.id",
+        )
+        .concat_with_param(
+            "WHERE login LIKE :login GROUP BY login",
+            (":login", &Query::patternize(pattern)),
+        )
+        .fetch_as(self.get_connection(), |row| Account {
+            login: row.get_unwrap(0),
+            links: row.get_unwrap(1),
+            admin: row.get_unwrap(2),
+            disabled: row.get_unwrap(3),
+        })
     }
     pub fn add_user(&self, login: &str, password: &str) -> DBResult<User> {
         let hashed = hash(password, 10).expect("Couldn't hash a password for some reason.");
-        self.get_connection().execute(
-            "INSERT INTO users(login, password) VALUES(?1, ?2)",
-            params![login, hashed],
-        )?;
-        Ok(User::new(self.get_connection().last_insert_rowid(), login))
-    }
-    pub fn del_user(&self, login: &str) -> DBResult<(User, bool)> {
-        if let Ok((u, c)) = self.find_user(login) {
-            if c == 0 || confirm(format!("User {} has {} links. Proceed?", u.login, c).as_ref()) {
-                self.get_connection()
-                    .execute("DELETE FROM users WHERE id = ?1", params![u.id])?;
-                Ok((u, true))
-            } else {
-                Ok((u, false))
-            }
+        self.add_hashed_user(login, &hashed)
+    }
+    /// Removes user along with owned links and tags. Users who still own any links are removed
+    /// only if `confirm` agrees to, given the user and number of their links.
+    pub fn del_user<F>(&self, login: &str, confirm: F) -> DBResult<(User, bool)>
+    where
+        F: FnOnce(&User, u32) -> bool,
+    {
+        let (u, c) = self.find_user(login)?;
+        if c == 0 || confirm(&u, c) {
+            self.get_connection()
+                .execute("DELETE FROM users WHERE id = ?1", params![u.id])?;
+            Ok((u, true))
         } else {
-            Err(UnknownUser)
+            Ok((u, false))
         }
     }
-    pub fn passwd_user(&self, login: &str) -> DBResult<User> {
-        if let Ok((u, _count)) = self.find_user(login) {
-            let pass = password(None, Some("New password"));
-            let hashed = hash(pass, 10).expect("Couldn't hash a password for some reason");
-            self.get_connection().execute(
-                "UPDATE users SET password = ?1 WHERE id = ?2",
-                params![hashed, u.id],
-            )?;
-            Ok(u)
-        } else {
-            Err(UnknownUser)
+    /// Creates users out of `login:hash` lines, with passwords already hashed by bcrypt
+    /// (like the ones generated by `htpasswd -B`). Empty lines and `#` comments are ignored.
+    /// Nothing gets imported if any line is malformed. Returns created users and logins
+    /// which were skipped as they already exist.
+    pub fn import_users(&self, contents: &str) -> DBResult<(Vec<User>, Vec<String>)> {
+        let mut entries = Vec::new();
+        for (n, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(':') {
+                Some((login, hashed)) if !login.trim().is_empty() && is_bcrypt(hashed.trim()) => {
+                    entries.push((login.trim(), hashed.trim()))
+                }
+                _ => {
+                    return Err(BadImport(format!(
+                        "line {}: expected login:bcrypt-hash",
+                        n + 1
+                    )))
+                }
+            }
         }
+        let (mut added, mut skipped) = (Vec::new(), Vec::new());
+        for (login, hashed) in entries {
+            match self.add_hashed_user(login, hashed) {
+                Ok(u) => added.push(u),
+                Err(DuplicatedUser(login)) => skipped.push(login),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((added, skipped))
     }
-    pub fn generate_key(&self, login: &str) -> DBResult<(User, String)> {
-        if let Ok((u, _count)) = self.find_user(login) {
-            let key = random_string(32);
-            self.get_connection().execute(
-                "UPDATE users SET api_key = ?1 WHERE id = ?2",
-                params![key, u.id],
-            )?;
-            Ok((u, key))
-        } else {
-            Err(UnknownUser)
+    pub fn passwd_user(&self, login: &str, password: &str) -> DBResult<User> {
+        let (u, _count) = self.find_user(login)?;
+        let hashed = hash(password, 10).expect("Couldn't hash a password for some reason");
+        self.get_connection().execute(
+            "UPDATE users SET password = ?1 WHERE id = ?2",
+            params![hashed, u.id],
+        )?;
+        Ok(u)
+    }
+    pub fn set_admin(&self, login: &str, is_admin: bool) -> DBResult<User> {
+        let (u, _count) = self.find_user(login)?;
+        self.get_connection().execute(
+            "UPDATE users SET is_admin = ?1 WHERE id = ?2",
+            params![is_admin, u.id],
+        )?;
+        Ok(u)
+    }
+    /// Disables (or enables back) user's account. Disabled user can't log in and their
+    /// tokens are refused. Sessions are ended right away.
+    pub fn set_disabled(&self, login: &str, is_disabled: bool) -> DBResult<User> {
+        let (u, _count) = self.find_user(login)?;
+        let conn = self.get_connection();
+        conn.execute(
+            "UPDATE users SET is_disabled = ?1 WHERE id = ?2",
+            params![is_disabled, u.id],
+        )?;
+        if is_disabled {
+            conn.execute("DELETE FROM sessions WHERE user_id = ?1", params![u.id])?;
         }
+        Ok(u)
+    }
+    fn add_hashed_user(&self, login: &str, hashed: &str) -> DBResult<User> {
+        self.get_connection()
+            .query_row(
+                "INSERT INTO users(login, password) VALUES(?1, ?2) RETURNING id",
+                params![login, hashed],
+                |row| row.get(0),
+            )
+            .map(|id| User::new(id, login))
+            .map_err(|e| match e {
+                SqliteFailure(f, _) if f.code == ErrorCode::ConstraintViolation => {
+                    DuplicatedUser(login.to_string())
+                }
+                e => e.into(),
+            })
+    }
+    pub fn is_admin(&self, user: &User) -> DBResult<bool> {
+        self.get_connection()
+            .query_row(
+                "SELECT is_admin FROM users WHERE id = ?1",
+                params![user.id],
+                |row| row.get(0),
+            )
+            .map_err(|_| UnknownUser)
     }
 }
 
+/// Checks if given string looks like a bcrypt hash, eg. `$2b$10$` followed by salt and hash.
+fn is_bcrypt(hashed: &str) -> bool {
+    let parts: Vec<_> = hashed.split('$').collect();
+    matches!(parts.as_slice(), ["", "2a" | "2b" | "2x" | "2y", cost, rest]
+        if cost.len() == 2 && cost.parse::<u32>().is_ok() && rest.len() == 53)
+}
+
 #[cfg(test)]
 mod test_user {
     use super::*;
+    use crate::db::DBError::{BadPassword, NotAdmin};
+    use crate::utils::random_string;
     use crate::vault::test_db::{auth, vault};
     use crate::Authentication;
     use rstest::*;
 
     #[rstest]
     fn test_add_new_user(vault: &Vault, #[with("boo")] auth: Option<Authentication>) {
-        let user_info = vault.user_info(&auth);
-        assert_eq!("boo", user_info.unwrap().login);
+        let session = vault.login(&auth, None);
+        assert_eq!("boo", session.unwrap().login);
+    }
+
+    #[rstest]
+    fn test_disabled_user(vault: &Vault, auth: Option<Authentication>) {
+        let login = vault.authenticate_user(&auth).unwrap().login;
+        let session = vault.login(&auth, None).unwrap();
+
+        vault.set_disabled(&login, true).unwrap();
+        assert!(vault.authenticate_user(&auth).is_err());
+        assert!(vault
+            .authenticate_user(&Authentication::from_token(Some(&session.token)))
+            .is_err());
+        assert!(vault.match_users(&login).unwrap()[0].disabled);
+
+        vault.set_disabled(&login, false).unwrap();
+        assert!(vault.authenticate_user(&auth).is_ok());
+    }
+
+    #[rstest]
+    fn test_admin_role(vault: &Vault, auth: Option<Authentication>) {
+        let login = vault.authenticate_user(&auth).unwrap().login;
+        assert!(matches!(vault.authorize_admin(&auth), Err(NotAdmin)));
+
+        vault.set_admin(&login, true).unwrap();
+        assert!(vault.authorize_admin(&auth).is_ok());
+        assert!(vault.match_users(&login).unwrap()[0].admin);
+        assert!(matches!(
+            vault.add_user(&login, "secret"),
+            Err(DuplicatedUser(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_exact_user_lookup(vault: &Vault, auth: Option<Authentication>) {
+        let login = vault.authenticate_user(&auth).unwrap().login;
+        let wildcards = [
+            "%".to_string(),
+            format!("{}%", &login[..1]),
+            format!("_{}", &login[1..]),
+        ];
+        for pattern in wildcards.iter() {
+            assert!(matches!(vault.find_user(pattern), Err(UnknownUser)));
+            assert!(matches!(
+                vault.set_disabled(pattern, true),
+                Err(UnknownUser)
+            ));
+            assert!(matches!(
+                vault.del_user(pattern, |_, _| true),
+                Err(UnknownUser)
+            ));
+        }
+        let (user, _) = vault.find_user(&login.to_uppercase()).unwrap();
+        assert_eq!(login, user.login.to_lowercase());
+        assert!(vault.authenticate_user(&auth).is_ok());
+    }
+
+    #[rstest]
+    fn test_add_duplicated_user(vault: &Vault) {
+        let login = random_string(8).to_lowercase();
+        let user = vault.add_user(&login, "secret").unwrap();
+        let (found, _) = vault.find_user(&login).unwrap();
+        assert_eq!(user.id, found.id);
+
+        for duplicate in [login.clone(), login.to_uppercase()].iter() {
+            assert!(matches!(
+                vault.add_user(duplicate, "secret"),
+                Err(DuplicatedUser(_))
+            ));
+        }
+    }
+
+    #[rstest]
+    fn test_delete_user(vault: &Vault, auth: Option<Authentication>) {
+        let login = vault.authenticate_user(&auth).unwrap().login;
+        vault.passwd_user(&login, "changed").unwrap();
+        assert!(matches!(vault.authenticate_user(&auth), Err(BadPassword)));
+
+        vault.del_user(&login, |_, _| true).unwrap();
+        assert!(vault.match_users(&login).unwrap().is_empty());
+    }
+
+    #[rstest]
+    fn test_import_users(vault: &Vault) {
+        let login = random_string(8).to_lowercase();
+        let hashed = hash("imported", 4).unwrap();
+        let contents = format!("# provisioned\n\n{}:{}\n", login, hashed);
+
+        let (added, skipped) = vault.import_users(&contents).unwrap();
+        assert_eq!((1, 0), (added.len(), skipped.len()));
+        let auth = Authentication::from_credentials(login.clone(), "imported".to_string());
+        assert!(vault.authenticate_user(&auth).is_ok());
+
+        let (added, skipped) = vault.import_users(&contents).unwrap();
+        assert_eq!((0, vec![login]), (added.len(), skipped));
+        assert!(matches!(
+            vault.import_users("someone:plaintext"),
+            Err(BadImport(_))
+        ));
     }
 }